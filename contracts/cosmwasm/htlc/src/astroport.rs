@@ -0,0 +1,417 @@
+//! Astroport backend for HTLC swaps, used on Neutron and Terra.
+//!
+//! Swaps go through the Astroport router, which resolves the pair for each hop.
+//! Spot prices come from the hop's pair reserves and fees from the factory's
+//! fee configuration for the pair type. Only native denoms are supported, as
+//! HTLCs are funded with bank coins.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_json_binary, Addr, Coin, CosmosMsg, Decimal, Deps, StdError, StdResult, Uint128, WasmMsg,
+};
+
+use crate::dex::{Dex, DexAdapter, SwapRoute};
+
+/// Astroport rejects a max spread above 50%; the swap is bounded by `minimum_receive` instead
+const MAX_ALLOWED_SPREAD: u64 = 50;
+
+#[cw_serde]
+pub enum AssetInfo {
+    Token { contract_addr: Addr },
+    NativeToken { denom: String },
+}
+
+#[cw_serde]
+pub struct Asset {
+    pub info: AssetInfo,
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub enum PairType {
+    Xyk {},
+    Stable {},
+    Custom(String),
+}
+
+#[cw_serde]
+pub enum SwapOperation {
+    AstroSwap {
+        offer_asset_info: AssetInfo,
+        ask_asset_info: AssetInfo,
+    },
+}
+
+#[cw_serde]
+pub enum RouterExecuteMsg {
+    ExecuteSwapOperations {
+        operations: Vec<SwapOperation>,
+        minimum_receive: Option<Uint128>,
+        to: Option<String>,
+        max_spread: Option<Decimal>,
+    },
+}
+
+#[cw_serde]
+pub enum RouterQueryMsg {
+    SimulateSwapOperations {
+        offer_amount: Uint128,
+        operations: Vec<SwapOperation>,
+    },
+}
+
+#[cw_serde]
+pub struct SimulateSwapOperationsResponse {
+    pub amount: Uint128,
+}
+
+#[cw_serde]
+pub enum PairQueryMsg {
+    Pair {},
+    Pool {},
+}
+
+#[cw_serde]
+pub struct PairInfo {
+    pub asset_infos: Vec<AssetInfo>,
+    pub contract_addr: Addr,
+    pub liquidity_token: Addr,
+    pub pair_type: PairType,
+}
+
+#[cw_serde]
+pub struct PoolResponse {
+    pub assets: Vec<Asset>,
+    pub total_share: Uint128,
+}
+
+#[cw_serde]
+pub enum FactoryQueryMsg {
+    FeeInfo { pair_type: PairType },
+}
+
+#[cw_serde]
+pub struct FeeInfoResponse {
+    pub fee_address: Option<Addr>,
+    pub total_fee_bps: u16,
+    pub maker_fee_bps: u16,
+}
+
+/// Astroport router and factory backend
+pub struct AstroportAdapter {
+    pub router: String,
+    pub factory: String,
+}
+
+impl DexAdapter for AstroportAdapter {
+    fn supports(&self, route: &SwapRoute) -> bool {
+        matches!(route.dex, Dex::Astroport { .. })
+    }
+
+    fn spot_price(&self, deps: Deps, token_in_denom: &str, route: &SwapRoute) -> StdResult<Decimal> {
+        let pool: PoolResponse = deps.querier.query_wasm_smart(pair(route)?, &PairQueryMsg::Pool {})?;
+
+        let reserve_in = reserve(&pool, token_in_denom)?;
+        let reserve_out = reserve(&pool, &route.token_out_denom)?;
+        if reserve_out.is_zero() {
+            return Err(StdError::generic_err(format!(
+                "Pair has no {} liquidity",
+                route.token_out_denom
+            )));
+        }
+
+        // Input per unit of output, as the trait documents
+        Ok(Decimal::from_ratio(reserve_in, reserve_out))
+    }
+
+    fn estimate_swap(&self, deps: Deps, token_in: &Coin, routes: &[SwapRoute]) -> StdResult<Uint128> {
+        let response: SimulateSwapOperationsResponse = deps.querier.query_wasm_smart(
+            &self.router,
+            &RouterQueryMsg::SimulateSwapOperations {
+                offer_amount: token_in.amount,
+                operations: swap_operations(&token_in.denom, routes),
+            },
+        )?;
+
+        Ok(response.amount)
+    }
+
+    fn swap_fee(&self, deps: Deps, route: &SwapRoute) -> StdResult<Decimal> {
+        let pair_info: PairInfo = deps.querier.query_wasm_smart(pair(route)?, &PairQueryMsg::Pair {})?;
+        let fee_info: FeeInfoResponse = deps.querier.query_wasm_smart(
+            &self.factory,
+            &FactoryQueryMsg::FeeInfo {
+                pair_type: pair_info.pair_type,
+            },
+        )?;
+
+        Ok(Decimal::from_ratio(fee_info.total_fee_bps as u128, 10_000u128))
+    }
+
+    fn swap_msg(
+        &self,
+        sender: &str,
+        token_in: &Coin,
+        routes: &[SwapRoute],
+        min_output_amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = RouterExecuteMsg::ExecuteSwapOperations {
+            operations: swap_operations(&token_in.denom, routes),
+            minimum_receive: Some(min_output_amount),
+            to: Some(sender.to_string()),
+            max_spread: Some(Decimal::percent(MAX_ALLOWED_SPREAD)),
+        };
+
+        Ok(WasmMsg::Execute {
+            contract_addr: self.router.clone(),
+            msg: to_json_binary(&msg)?,
+            funds: vec![token_in.clone()],
+        }
+        .into())
+    }
+}
+
+fn pair(route: &SwapRoute) -> StdResult<&str> {
+    match &route.dex {
+        Dex::Astroport { pair } => Ok(pair),
+        _ => Err(StdError::generic_err(format!(
+            "Swap hop to {} is not an Astroport pair",
+            route.token_out_denom
+        ))),
+    }
+}
+
+fn reserve(pool: &PoolResponse, denom: &str) -> StdResult<Uint128> {
+    pool.assets
+        .iter()
+        .find(|asset| asset.info == native(denom))
+        .map(|asset| asset.amount)
+        .ok_or_else(|| StdError::generic_err(format!("Pair does not hold {}", denom)))
+}
+
+fn native(denom: &str) -> AssetInfo {
+    AssetInfo::NativeToken {
+        denom: denom.to_string(),
+    }
+}
+
+fn swap_operations(token_in_denom: &str, routes: &[SwapRoute]) -> Vec<SwapOperation> {
+    let mut offer_denom = token_in_denom.to_string();
+    routes
+        .iter()
+        .map(|route| {
+            let operation = SwapOperation::AstroSwap {
+                offer_asset_info: native(&offer_denom),
+                ask_asset_info: native(&route.token_out_denom),
+            };
+            offer_denom = route.token_out_denom.clone();
+            operation
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{
+        coins, from_json, ContractResult, SystemError, SystemResult, WasmQuery,
+    };
+    use fusion_plus::{DexConfig, ProtocolConfig};
+    use crate::contract::{execute, instantiate};
+    use crate::dex::SwapParams;
+    use crate::error::ContractError;
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+
+    fn mock_astroport(querier: &mut cosmwasm_std::testing::MockQuerier) {
+        querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, msg } => {
+                let response = match contract_addr.as_str() {
+                    // 1.04 uatom per untrn
+                    "pair1" => match from_json(msg).unwrap() {
+                        PairQueryMsg::Pool {} => to_json_binary(&PoolResponse {
+                            assets: vec![
+                                Asset { info: native("uatom"), amount: Uint128::new(1_040_000) },
+                                Asset { info: native("untrn"), amount: Uint128::new(1_000_000) },
+                            ],
+                            total_share: Uint128::new(1_000_000),
+                        }),
+                        PairQueryMsg::Pair {} => to_json_binary(&PairInfo {
+                            asset_infos: vec![native("uatom"), native("untrn")],
+                            contract_addr: Addr::unchecked("pair1"),
+                            liquidity_token: Addr::unchecked("lp1"),
+                            pair_type: PairType::Xyk {},
+                        }),
+                    },
+                    // 95% output
+                    "router" => match from_json(msg).unwrap() {
+                        RouterQueryMsg::SimulateSwapOperations { offer_amount, .. } => {
+                            to_json_binary(&SimulateSwapOperationsResponse {
+                                amount: offer_amount * Decimal::percent(95),
+                            })
+                        }
+                    },
+                    "factory" => to_json_binary(&FeeInfoResponse {
+                        fee_address: None,
+                        total_fee_bps: 30,
+                        maker_fee_bps: 0,
+                    }),
+                    _ => {
+                        return SystemResult::Err(SystemError::NoSuchContract {
+                            addr: contract_addr.clone(),
+                        })
+                    }
+                };
+                SystemResult::Ok(ContractResult::Ok(response.unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "wasm".to_string(),
+            }),
+        });
+    }
+
+    #[test]
+    fn test_swap_through_astroport() {
+        let mut deps = mock_dependencies();
+        mock_astroport(&mut deps.querier);
+        let env = mock_env();
+
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), InstantiateMsg { admin: None }).unwrap();
+
+        let mut config = ProtocolConfig::default();
+        config.swap.dex = DexConfig::Astroport {
+            router: "router".to_string(),
+            factory: "factory".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), ExecuteMsg::UpdateConfig { config }).unwrap();
+
+        let swap_params = SwapParams {
+            routes: vec![SwapRoute {
+                pool_id: 0,
+                token_out_denom: "untrn".to_string(),
+                dex: Dex::Astroport { pair: "pair1".to_string() },
+            }],
+            min_output_amount: Uint128::new(90),
+            slippage_tolerance: Decimal::percent(1),
+            max_input_amount: None,
+        };
+        let msg = ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "neutron-1".to_string(),
+            target_address: "neutron1abc...".to_string(),
+            swap_params: swap_params.clone(),
+            preimage_length: None,
+            transferable: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        let estimated_output = res.attributes.iter().find(|a| a.key == "estimated_output").unwrap();
+        assert_eq!(estimated_output.value, "95");
+
+        // Execution swaps through the router with the re-quoted minimum
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_0".to_string(),
+            swap_params,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("sender", &[]), msg).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, funds }) => {
+                assert_eq!(contract_addr, "router");
+                assert_eq!(funds, &coins(100, "uatom"));
+                let RouterExecuteMsg::ExecuteSwapOperations { operations, minimum_receive, to, .. } =
+                    from_json(msg).unwrap();
+                assert_eq!(operations.len(), 1);
+                assert_eq!(minimum_receive, Some(Uint128::new(94)));
+                assert_eq!(to, Some(env.contract.address.to_string()));
+            }
+            _ => panic!("Expected router execute message"),
+        }
+    }
+
+    #[test]
+    fn test_astroport_config_requires_valid_addresses() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), InstantiateMsg { admin: None }).unwrap();
+
+        let mut config = ProtocolConfig::default();
+        config.swap.dex = DexConfig::Astroport {
+            router: "router".to_string(),
+            factory: "".to_string(),
+        };
+        let err = execute(deps.as_mut(), env, mock_info("admin", &[]), ExecuteMsg::UpdateConfig { config }).unwrap_err();
+        match err {
+            ContractError::Std(_) => {}
+            _ => panic!("Expected address validation error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_astroport_rejects_osmosis_routes() {
+        let mut deps = mock_dependencies();
+        mock_astroport(&mut deps.querier);
+        let env = mock_env();
+
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), InstantiateMsg { admin: None }).unwrap();
+
+        let mut config = ProtocolConfig::default();
+        config.swap.dex = DexConfig::Astroport {
+            router: "router".to_string(),
+            factory: "factory".to_string(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), ExecuteMsg::UpdateConfig { config }).unwrap();
+
+        let msg = ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "neutron-1".to_string(),
+            target_address: "neutron1abc...".to_string(),
+            swap_params: SwapParams {
+                routes: vec![SwapRoute {
+                    pool_id: 1,
+                    token_out_denom: "untrn".to_string(),
+                    dex: Dex::Osmosis,
+                }],
+                min_output_amount: Uint128::new(90),
+                slippage_tolerance: Decimal::percent(1),
+                max_input_amount: None,
+            },
+            preimage_length: None,
+            transferable: None,
+        };
+        let err = execute(deps.as_mut(), env, mock_info("sender", &coins(100, "uatom")), msg).unwrap_err();
+        match err {
+            ContractError::Std(StdError::GenericErr { msg, .. }) => {
+                assert!(msg.contains("not on the configured DEX"))
+            }
+            _ => panic!("Expected generic error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    fn test_astroport_pricing() {
+        let mut deps = mock_dependencies();
+        mock_astroport(&mut deps.querier);
+        let adapter = AstroportAdapter {
+            router: "router".to_string(),
+            factory: "factory".to_string(),
+        };
+        let route = SwapRoute {
+            pool_id: 0,
+            token_out_denom: "untrn".to_string(),
+            dex: Dex::Astroport { pair: "pair1".to_string() },
+        };
+
+        let spot_price = adapter.spot_price(deps.as_ref(), "uatom", &route).unwrap();
+        assert_eq!(spot_price, Decimal::from_ratio(104u128, 100u128));
+
+        let swap_fee = adapter.swap_fee(deps.as_ref(), &route).unwrap();
+        assert_eq!(swap_fee, Decimal::permille(3));
+
+        let out = adapter.estimate_swap(deps.as_ref(), &Coin::new(1000, "uatom"), &[route]).unwrap();
+        assert_eq!(out, Uint128::new(950));
+    }
+}
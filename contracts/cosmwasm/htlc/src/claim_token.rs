@@ -0,0 +1,267 @@
+use cosmwasm_std::{
+    Addr, Binary, Deps, DepsMut, Empty, MessageInfo, Order, Response, StdError, StdResult,
+    Storage,
+};
+use cw721::{
+    ContractInfoResponse, Cw721ReceiveMsg, NftInfoResponse, NumTokensResponse, OwnerOfResponse,
+    TokensResponse,
+};
+use cw_storage_plus::Bound;
+
+use crate::constants::{CLAIM_TOKEN_NAME, CLAIM_TOKEN_SYMBOL};
+use crate::contract::htlc_response;
+use crate::error::ContractError;
+use crate::msg::HtlcResponse;
+use crate::state::{Htlc, CLAIM_TOKENS, CLAIM_TOKEN_COUNT, HTLCS};
+
+/// Mint a claim token for an HTLC, owned by its receiver.
+/// The token id is the HTLC id.
+pub fn mint(storage: &mut dyn Storage, owner: &Addr, token_id: &str) -> StdResult<()> {
+    CLAIM_TOKENS.save(storage, (owner, token_id), &Empty {})?;
+    let count = CLAIM_TOKEN_COUNT.load(storage)?;
+    CLAIM_TOKEN_COUNT.save(storage, &(count + 1))
+}
+
+/// Burn the claim token of a settled HTLC so the position can no longer be traded
+pub fn burn(storage: &mut dyn Storage, owner: &Addr, token_id: &str) -> StdResult<()> {
+    CLAIM_TOKENS.remove(storage, (owner, token_id));
+    let count = CLAIM_TOKEN_COUNT.load(storage)?;
+    CLAIM_TOKEN_COUNT.save(storage, &count.saturating_sub(1))
+}
+
+pub fn transfer_nft(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: String,
+    token_id: String,
+) -> Result<Response, ContractError> {
+    let recipient_addr = deps.api.addr_validate(&recipient)?;
+    transfer_claim(deps, &info.sender, &recipient_addr, &token_id)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "transfer_nft")
+        .add_attribute("sender", info.sender)
+        .add_attribute("recipient", recipient_addr)
+        .add_attribute("token_id", token_id))
+}
+
+pub fn send_nft(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract: String,
+    token_id: String,
+    msg: Binary,
+) -> Result<Response, ContractError> {
+    let contract_addr = deps.api.addr_validate(&contract)?;
+    transfer_claim(deps, &info.sender, &contract_addr, &token_id)?;
+
+    // Notify the receiving contract, as cw721 requires
+    let receive_msg = Cw721ReceiveMsg {
+        sender: info.sender.to_string(),
+        token_id: token_id.clone(),
+        msg,
+    };
+
+    Ok(Response::new()
+        .add_message(receive_msg.into_cosmos_msg(contract_addr.clone())?)
+        .add_attribute("action", "send_nft")
+        .add_attribute("sender", info.sender)
+        .add_attribute("recipient", contract_addr)
+        .add_attribute("token_id", token_id))
+}
+
+/// Move a claim to a new owner, who becomes the effective receiver of the HTLC
+fn transfer_claim(
+    deps: DepsMut,
+    sender: &Addr,
+    recipient: &Addr,
+    token_id: &str,
+) -> Result<(), ContractError> {
+    let mut htlc = HTLCS.load(deps.storage, token_id)?;
+
+    if !htlc.claim_token {
+        return Err(ContractError::ClaimNotTransferable {});
+    }
+
+    if htlc.withdrawn {
+        return Err(ContractError::AlreadyWithdrawn {});
+    }
+
+    if htlc.refunded {
+        return Err(ContractError::AlreadyRefunded {});
+    }
+
+    if *sender != htlc.receiver {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    CLAIM_TOKENS.remove(deps.storage, (&htlc.receiver, token_id));
+    CLAIM_TOKENS.save(deps.storage, (recipient, token_id), &Empty {})?;
+
+    htlc.receiver = recipient.clone();
+    HTLCS.save(deps.storage, token_id, &htlc)?;
+
+    Ok(())
+}
+
+fn load_live_claim(deps: Deps, token_id: &str) -> StdResult<Htlc> {
+    let htlc = HTLCS.load(deps.storage, token_id)?;
+    if !htlc.claim_token || htlc.withdrawn || htlc.refunded {
+        return Err(StdError::not_found("claim token"));
+    }
+    Ok(htlc)
+}
+
+pub fn query_owner_of(deps: Deps, token_id: String) -> StdResult<OwnerOfResponse> {
+    let htlc = load_live_claim(deps, &token_id)?;
+    Ok(OwnerOfResponse {
+        owner: htlc.receiver.to_string(),
+        approvals: vec![],
+    })
+}
+
+pub fn query_nft_info(deps: Deps, token_id: String) -> StdResult<NftInfoResponse<HtlcResponse>> {
+    let htlc = load_live_claim(deps, &token_id)?;
+    Ok(NftInfoResponse {
+        token_uri: None,
+        extension: htlc_response(token_id, htlc),
+    })
+}
+
+pub fn query_num_tokens(deps: Deps) -> StdResult<NumTokensResponse> {
+    let count = CLAIM_TOKEN_COUNT.load(deps.storage)?;
+    Ok(NumTokensResponse { count })
+}
+
+pub fn query_tokens(
+    deps: Deps,
+    owner: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let limit = limit.unwrap_or(10).min(100);
+    let owner_addr = deps.api.addr_validate(&owner)?;
+    let start = start_after.as_ref().map(|s| Bound::exclusive(s.as_str()));
+
+    let tokens: StdResult<Vec<String>> = CLAIM_TOKENS
+        .prefix(&owner_addr)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit as usize)
+        .collect();
+
+    Ok(TokensResponse { tokens: tokens? })
+}
+
+pub fn query_contract_info() -> ContractInfoResponse {
+    ContractInfoResponse {
+        name: CLAIM_TOKEN_NAME.to_string(),
+        symbol: CLAIM_TOKEN_SYMBOL.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
+    use cosmwasm_std::{coins, BankMsg, CosmosMsg};
+    use sha2::{Digest, Sha256};
+    use crate::contract::{execute, instantiate};
+    use crate::msg::{ExecuteMsg, InstantiateMsg};
+
+    #[test]
+    fn test_claim_token_transfer_and_withdraw() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("admin", &[]);
+        
+        // Instantiate
+        let msg = InstantiateMsg { admin: None };
+        instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+        
+        // Create HTLC with a transferable claim
+        let secret = [7u8; 32];
+        let mut hasher = Sha256::new();
+        hasher.update(secret);
+        let hashlock = hex::encode(hasher.finalize());
+        
+        let msg = ExecuteMsg::CreateHtlc {
+            receiver: "resolver1".to_string(),
+            hashlock,
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: Some(true),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        
+        let owner = query_owner_of(deps.as_ref(), "htlc_0".to_string()).unwrap();
+        assert_eq!(owner.owner, "resolver1");
+        assert_eq!(query_num_tokens(deps.as_ref()).unwrap().count, 1);
+        
+        // Only the current holder may transfer the claim
+        let msg = ExecuteMsg::TransferNft {
+            recipient: "resolver2".to_string(),
+            token_id: "htlc_0".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("sender", &[]), msg.clone()).unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("Expected Unauthorized error, got {:?}", err),
+        }
+        execute(deps.as_mut(), env.clone(), mock_info("resolver1", &[]), msg).unwrap();
+        
+        // The transfer updates the stored receiver and the owner index
+        let htlc = HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        assert_eq!(htlc.receiver, "resolver2");
+        let tokens = query_tokens(deps.as_ref(), "resolver1".to_string(), None, None).unwrap();
+        assert!(tokens.tokens.is_empty());
+        let tokens = query_tokens(deps.as_ref(), "resolver2".to_string(), None, None).unwrap();
+        assert_eq!(tokens.tokens, vec!["htlc_0".to_string()]);
+        
+        // Withdrawal pays the holder and burns the token
+        let msg = ExecuteMsg::Withdraw {
+            htlc_id: "htlc_0".to_string(),
+            secret: hex::encode(secret),
+        };
+        let res = execute(deps.as_mut(), env, mock_info("anyone", &[]), msg).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, .. }) => assert_eq!(to_address, "resolver2"),
+            _ => panic!("Expected bank send message"),
+        }
+        assert!(query_owner_of(deps.as_ref(), "htlc_0".to_string()).is_err());
+        assert_eq!(query_num_tokens(deps.as_ref()).unwrap().count, 0);
+    }
+
+    #[test]
+    fn test_transfer_requires_claim_token() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("admin", &[]);
+        
+        // Instantiate
+        let msg = InstantiateMsg { admin: None };
+        instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+        
+        let msg = ExecuteMsg::CreateHtlc {
+            receiver: "resolver1".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        
+        let msg = ExecuteMsg::TransferNft {
+            recipient: "resolver2".to_string(),
+            token_id: "htlc_0".to_string(),
+        };
+        let err = execute(deps.as_mut(), env, mock_info("resolver1", &[]), msg).unwrap_err();
+        match err {
+            ContractError::ClaimNotTransferable {} => {}
+            _ => panic!("Expected ClaimNotTransferable error, got {:?}", err),
+        }
+    }
+}
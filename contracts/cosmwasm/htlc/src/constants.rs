@@ -25,8 +25,20 @@ pub const MAX_HTLCS_PER_SENDER: u32 = 100;
 /// Hashlock length for SHA256 (in hex characters)
 pub const HASHLOCK_LENGTH: usize = 64;
 
+/// Default secret preimage length in bytes, matching the bytes32 secret of the Ethereum HTLC
+pub const DEFAULT_PREIMAGE_LENGTH: u32 = 32;
+
+/// Maximum declarable secret preimage length in bytes
+pub const MAX_PREIMAGE_LENGTH: u32 = 64;
+
+/// cw721 collection name for transferable HTLC claim tokens
+pub const CLAIM_TOKEN_NAME: &str = "Fusion HTLC Claim";
+
+/// cw721 collection symbol for transferable HTLC claim tokens
+pub const CLAIM_TOKEN_SYMBOL: &str = "FHTLC";
+
 /// Default sender ID for Osmosis poolmanager queries (0 = system/no specific sender)
 pub const DEFAULT_SENDER_ID: u64 = 0;
 
 /// Standard decimal precision for token amounts (18 decimals)
-pub const TOKEN_DECIMAL_PRECISION: u32 = 18;
\ No newline at end of file
+pub const TOKEN_DECIMAL_PRECISION: u32 = 18;
@@ -1,23 +1,55 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
-    Response, StdResult, Uint128,
+    entry_point, to_json_binary, BankMsg, Binary, Coin, Deps, DepsMut, Empty, Env, MessageInfo,
+    Order, Reply, Response, StdError, StdResult, SubMsg,
+};
+#[cfg(feature = "osmosis")]
+use cosmwasm_std::{CosmosMsg, Uint128};
+#[cfg(feature = "osmosis")]
+use osmosis_std::types::ibc::applications::transfer::v1::{MsgTransfer, MsgTransferResponse};
+#[cfg(feature = "osmosis")]
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    MsgSwapExactAmountOut, MsgSwapExactAmountOutResponse,
 };
-use osmosis_std::types::osmosis::poolmanager::v1beta1::MsgSwapExactAmountIn;
 use cw2::set_contract_version;
 use cw_storage_plus::Bound;
 use hex;
 use sha2::{Digest, Sha256};
-use fusion_plus::ProtocolConfig;
+#[cfg(feature = "osmosis")]
+use std::str::FromStr;
+use fusion_plus::{ConfigResponse, DexConfig, ProtocolConfig};
 
-use crate::constants::HASHLOCK_LENGTH;
+use crate::constants::{DEFAULT_PREIMAGE_LENGTH, HASHLOCK_LENGTH, MAX_PREIMAGE_LENGTH};
+#[cfg(feature = "osmosis")]
+use crate::constants::TIMEOUT_BUFFER;
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, HtlcResponse, InstantiateMsg, ListHtlcsResponse, QueryMsg};
-use crate::state::{Config, Htlc, CONFIG, HTLCS, HTLC_COUNT};
-use crate::dex::{self, SwapParams, SwapRoute};
+use crate::msg::{
+    ExecuteMsg, HtlcExpiryResponse, HtlcResponse, InstantiateMsg, ListHtlcExpiryResponse,
+    ListHtlcsResponse, QueryMsg, SecretResponse,
+};
+#[cfg(feature = "osmosis")]
+use crate::msg::{IBCLifecycleComplete, SudoMsg};
+use crate::state::{
+    Config, Htlc, IbcOrigin, CLAIM_TOKEN_COUNT, CONFIG, HTLCS, HTLC_COUNT, PENDING_SWAP,
+    PENDING_SWAP_BALANCE, SECRETS, TIMELOCK_INDEX,
+};
+#[cfg(feature = "osmosis")]
+use crate::state::{IBC_REFUNDS, PENDING_IBC_REFUND};
+use crate::claim_token;
+#[cfg(feature = "osmosis")]
+use crate::ibc_hooks;
+use crate::dex::{self, SwapParams, SwapQuote};
+#[cfg(feature = "osmosis")]
+use crate::dex::SwapRoute;
 
 const CONTRACT_NAME: &str = "crates.io:fusion-htlc";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+#[cfg(feature = "osmosis")]
+const IBC_REFUND_REPLY_ID: u64 = 1;
+#[cfg(feature = "osmosis")]
+const SWAP_EXACT_OUT_REPLY_ID: u64 = 2;
+const SWAP_EXACT_IN_REPLY_ID: u64 = 3;
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
@@ -31,14 +63,17 @@ pub fn instantiate(
         .transpose()?
         .unwrap_or_else(|| info.sender.clone());
 
+    let protocol_config = ProtocolConfig::default();
+    validate_protocol_config(deps.as_ref(), &protocol_config)?;
     let config = Config {
         admin: admin.clone(),
-        protocol_config: ProtocolConfig::default(),
+        protocol_config,
     };
 
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
     CONFIG.save(deps.storage, &config)?;
     HTLC_COUNT.save(deps.storage, &0u64)?;
+    CLAIM_TOKEN_COUNT.save(deps.storage, &0u64)?;
 
     Ok(Response::new()
         .add_attribute("method", "instantiate")
@@ -59,6 +94,8 @@ pub fn execute(
             timelock,
             target_chain,
             target_address,
+            preimage_length,
+            transferable,
         } => create_htlc(
             deps,
             env,
@@ -68,6 +105,9 @@ pub fn execute(
             timelock,
             target_chain,
             target_address,
+            preimage_length,
+            transferable.unwrap_or(false),
+            None,
         ),
         ExecuteMsg::Withdraw { htlc_id, secret } => withdraw(deps, env, info, htlc_id, secret),
         ExecuteMsg::Refund { htlc_id } => refund(deps, env, info, htlc_id),
@@ -78,6 +118,8 @@ pub fn execute(
             target_chain,
             target_address,
             swap_params,
+            preimage_length,
+            transferable,
         } => create_htlc_with_swap(
             deps,
             env,
@@ -88,13 +130,48 @@ pub fn execute(
             target_chain,
             target_address,
             swap_params,
+            preimage_length,
+            transferable.unwrap_or(false),
         ),
         ExecuteMsg::ExecuteSwapAndLock { htlc_id, swap_params } => {
             execute_swap_and_lock(deps, env, info, htlc_id, swap_params)
         }
+        ExecuteMsg::UpdateConfig { config } => update_config(deps, info, config),
+        #[cfg(feature = "osmosis")]
+        ExecuteMsg::CreateHtlcFromIbc {
+            channel,
+            original_sender,
+            receiver,
+            hashlock,
+            timelock,
+            target_chain,
+            target_address,
+            preimage_length,
+        } => create_htlc_from_ibc(
+            deps,
+            env,
+            info,
+            channel,
+            original_sender,
+            receiver,
+            hashlock,
+            timelock,
+            target_chain,
+            target_address,
+            preimage_length,
+        ),
+        #[cfg(not(feature = "osmosis"))]
+        ExecuteMsg::CreateHtlcFromIbc { .. } => Err(ContractError::IbcHooksUnsupported {}),
+        ExecuteMsg::TransferNft { recipient, token_id } => {
+            claim_token::transfer_nft(deps, info, recipient, token_id)
+        }
+        ExecuteMsg::SendNft { contract, token_id, msg } => {
+            claim_token::send_nft(deps, info, contract, token_id, msg)
+        }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn create_htlc(
     deps: DepsMut,
     env: Env,
@@ -104,9 +181,13 @@ fn create_htlc(
     timelock: u64,
     target_chain: String,
     target_address: String,
+    preimage_length: Option<u32>,
+    transferable: bool,
+    ibc_origin: Option<IbcOrigin>,
 ) -> Result<Response, ContractError> {
     // Validate inputs
     let receiver_addr = deps.api.addr_validate(&receiver)?;
+    let preimage_length = validate_preimage_length(preimage_length)?;
 
     if info.funds.is_empty() {
         return Err(ContractError::InvalidAmount {});
@@ -147,6 +228,7 @@ fn create_htlc(
         receiver: receiver_addr.clone(),
         amount: info.funds.clone(),
         hashlock: hashlock.clone(),
+        preimage_length,
         timelock,
         withdrawn: false,
         refunded: false,
@@ -154,21 +236,93 @@ fn create_htlc(
         target_address: target_address.clone(),
         swap_params: None,
         swap_executed: false,
+        creation_quote: None,
+        execution_quote: None,
+        secret: None,
+        claim_token: transferable,
+        ibc_origin,
     };
 
     // Save HTLC
     HTLCS.save(deps.storage, &htlc_id, &htlc)?;
+    TIMELOCK_INDEX.save(deps.storage, (timelock, &htlc_id), &Empty {})?;
     HTLC_COUNT.save(deps.storage, &(count + 1))?;
 
-    Ok(Response::new()
+    let mut response = Response::new()
         .add_attribute("method", "create_htlc")
         .add_attribute("htlc_id", &htlc_id)
         .add_attribute("sender", info.sender)
-        .add_attribute("receiver", receiver_addr)
+        .add_attribute("receiver", receiver_addr.clone())
         .add_attribute("hashlock", hashlock)
         .add_attribute("timelock", timelock.to_string())
         .add_attribute("target_chain", target_chain)
-        .add_attribute("target_address", target_address))
+        .add_attribute("target_address", target_address);
+
+    // Represent the receiver's claim as a transferable cw721 token
+    if transferable {
+        claim_token::mint(deps.storage, &receiver_addr, &htlc_id)?;
+        response = response.add_attribute("claim_token_id", &htlc_id);
+    }
+
+    Ok(response)
+}
+
+#[cfg(feature = "osmosis")]
+#[allow(clippy::too_many_arguments)]
+fn create_htlc_from_ibc(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    channel: String,
+    original_sender: String,
+    receiver: String,
+    hashlock: String,
+    timelock: u64,
+    target_chain: String,
+    target_address: String,
+    preimage_length: Option<u32>,
+) -> Result<Response, ContractError> {
+    // ibc-hooks executes as an address derived from the source channel and original
+    // sender, which proves the funds arrived over that channel from that sender
+    let prefix = ibc_hooks::bech32_prefix(&env.contract.address)?;
+    let derived = ibc_hooks::derive_intermediate_sender(&channel, &original_sender, prefix)?;
+    if info.sender != derived {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let ibc_origin = IbcOrigin {
+        channel: channel.clone(),
+        original_sender: original_sender.clone(),
+    };
+
+    let res = create_htlc(
+        deps,
+        env,
+        info,
+        receiver,
+        hashlock,
+        timelock,
+        target_chain,
+        target_address,
+        preimage_length,
+        false,
+        Some(ibc_origin),
+    )?;
+
+    Ok(res
+        .add_attribute("channel", channel)
+        .add_attribute("original_sender", original_sender))
+}
+
+fn validate_preimage_length(preimage_length: Option<u32>) -> Result<u32, ContractError> {
+    let length = preimage_length.unwrap_or(DEFAULT_PREIMAGE_LENGTH);
+    if length == 0 || length > MAX_PREIMAGE_LENGTH {
+        return Err(ContractError::InvalidPreimageLength {
+            length,
+            max: MAX_PREIMAGE_LENGTH,
+        });
+    }
+    Ok(length)
 }
 
 fn withdraw(
@@ -191,6 +345,14 @@ fn withdraw(
     // Decode secret from hex
     let secret_bytes = hex::decode(&secret).map_err(|_| ContractError::InvalidHashFormat {})?;
 
+    // Enforce the declared preimage length so the secret stays usable on every leg
+    if secret_bytes.len() != htlc.preimage_length as usize {
+        return Err(ContractError::InvalidSecretLength {
+            expected: htlc.preimage_length,
+            actual: secret_bytes.len() as u32,
+        });
+    }
+
     // Calculate hash of secret
     let mut hasher = Sha256::new();
     hasher.update(&secret_bytes);
@@ -202,11 +364,18 @@ fn withdraw(
         return Err(ContractError::InvalidSecret {});
     }
 
-    // Mark as withdrawn
+    // Mark as withdrawn and persist the preimage so the counterparty leg
+    // can be completed from chain state alone
     htlc.withdrawn = true;
+    htlc.secret = Some(secret.clone());
     HTLCS.save(deps.storage, &htlc_id, &htlc)?;
+    SECRETS.save(deps.storage, &htlc.hashlock, &secret)?;
+    TIMELOCK_INDEX.remove(deps.storage, (htlc.timelock, &htlc_id));
+    if htlc.claim_token {
+        claim_token::burn(deps.storage, &htlc.receiver, &htlc_id)?;
+    }
 
-    // Transfer funds to receiver
+    // Transfer funds to receiver (the claim token holder if one was minted)
     let bank_msg = BankMsg::Send {
         to_address: htlc.receiver.to_string(),
         amount: htlc.amount,
@@ -235,7 +404,9 @@ fn refund(
         return Err(ContractError::AlreadyRefunded {});
     }
 
-    if info.sender != htlc.sender {
+    // Nobody controls the derived sender of hooks-created HTLCs, so anyone may
+    // trigger their refund back to the original sender
+    if htlc.ibc_origin.is_none() && info.sender != htlc.sender {
         return Err(ContractError::Unauthorized {});
     }
 
@@ -246,6 +417,15 @@ fn refund(
     // Mark as refunded
     htlc.refunded = true;
     HTLCS.save(deps.storage, &htlc_id, &htlc)?;
+    TIMELOCK_INDEX.remove(deps.storage, (htlc.timelock, &htlc_id));
+    if htlc.claim_token {
+        claim_token::burn(deps.storage, &htlc.receiver, &htlc_id)?;
+    }
+
+    #[cfg(feature = "osmosis")]
+    if let Some(origin) = htlc.ibc_origin {
+        return refund_over_ibc(deps, env, htlc_id, origin, htlc.amount);
+    }
 
     // Transfer funds back to sender
     let bank_msg = BankMsg::Send {
@@ -259,6 +439,140 @@ fn refund(
         .add_attribute("htlc_id", htlc_id))
 }
 
+#[cfg(feature = "osmosis")]
+fn refund_over_ibc(
+    deps: DepsMut,
+    env: Env,
+    htlc_id: String,
+    origin: IbcOrigin,
+    amount: Vec<Coin>,
+) -> Result<Response, ContractError> {
+    // Hooks-created HTLCs are funded by a single ICS-20 transfer
+    let token = amount.into_iter().next().ok_or(ContractError::InvalidAmount {})?;
+
+    let transfer = MsgTransfer {
+        source_port: "transfer".to_string(),
+        source_channel: origin.channel.clone(),
+        token: Some(osmosis_std::types::cosmos::base::v1beta1::Coin {
+            denom: token.denom,
+            amount: token.amount.to_string(),
+        }),
+        sender: env.contract.address.to_string(),
+        receiver: origin.original_sender.clone(),
+        timeout_height: None,
+        timeout_timestamp: env.block.time.plus_seconds(TIMEOUT_BUFFER).nanos(),
+        memo: ibc_hooks::ibc_callback_memo(&env.contract.address)?,
+    };
+
+    // The packet sequence is only known from the transfer reply
+    PENDING_IBC_REFUND.save(deps.storage, &htlc_id)?;
+
+    Ok(Response::new()
+        .add_submessage(SubMsg::reply_on_success(transfer, IBC_REFUND_REPLY_ID))
+        .add_attribute("method", "refund")
+        .add_attribute("htlc_id", htlc_id)
+        .add_attribute("refund_channel", origin.channel)
+        .add_attribute("refund_to", origin.original_sender))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        #[cfg(feature = "osmosis")]
+        IBC_REFUND_REPLY_ID => {
+            let response: MsgTransferResponse = msg.result.try_into()?;
+            let htlc_id = PENDING_IBC_REFUND.load(deps.storage)?;
+            PENDING_IBC_REFUND.remove(deps.storage);
+
+            let htlc = HTLCS.load(deps.storage, &htlc_id)?;
+            let channel = htlc
+                .ibc_origin
+                .map(|origin| origin.channel)
+                .ok_or(ContractError::HtlcNotFound {})?;
+            IBC_REFUNDS.save(deps.storage, (&channel, response.sequence), &htlc_id)?;
+
+            Ok(Response::new()
+                .add_attribute("method", "refund_reply")
+                .add_attribute("htlc_id", htlc_id)
+                .add_attribute("packet_sequence", response.sequence.to_string()))
+        }
+        #[cfg(feature = "osmosis")]
+        SWAP_EXACT_OUT_REPLY_ID => swap_exact_out_reply(deps, msg),
+        SWAP_EXACT_IN_REPLY_ID => swap_exact_in_reply(deps, env),
+        id => Err(StdError::generic_err(format!("Unknown reply id: {}", id)).into()),
+    }
+}
+
+/// ibc-hooks only exists on Osmosis, so builds without it expose no sudo entry point
+#[cfg(feature = "osmosis")]
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCAck {
+            channel,
+            sequence,
+            success,
+            ..
+        }) => ibc_refund_complete(deps, channel, sequence, success),
+        SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCTimeout { channel, sequence }) => {
+            ibc_refund_complete(deps, channel, sequence, false)
+        }
+    }
+}
+
+#[cfg(feature = "osmosis")]
+fn ibc_refund_complete(
+    deps: DepsMut,
+    channel: String,
+    sequence: u64,
+    success: bool,
+) -> Result<Response, ContractError> {
+    let htlc_id = IBC_REFUNDS.load(deps.storage, (&channel, sequence))?;
+    IBC_REFUNDS.remove(deps.storage, (&channel, sequence));
+
+    // A failed or timed out transfer returns the funds to this contract,
+    // so the HTLC becomes refundable again
+    if !success {
+        let mut htlc = HTLCS.load(deps.storage, &htlc_id)?;
+        htlc.refunded = false;
+        HTLCS.save(deps.storage, &htlc_id, &htlc)?;
+        TIMELOCK_INDEX.save(deps.storage, (htlc.timelock, &htlc_id), &Empty {})?;
+    }
+
+    Ok(Response::new()
+        .add_attribute("method", "ibc_lifecycle_complete")
+        .add_attribute("htlc_id", htlc_id)
+        .add_attribute("success", success.to_string()))
+}
+
+fn update_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    protocol_config: ProtocolConfig,
+) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    
+    validate_protocol_config(deps.as_ref(), &protocol_config)?;
+    config.protocol_config = protocol_config;
+    CONFIG.save(deps.storage, &config)?;
+    
+    Ok(Response::new().add_attribute("method", "update_config"))
+}
+
+/// Validate protocol parameters, including the Astroport contract addresses if configured
+fn validate_protocol_config(deps: Deps, protocol_config: &ProtocolConfig) -> Result<(), ContractError> {
+    protocol_config.validate()?;
+    if let DexConfig::Astroport { router, factory } = &protocol_config.swap.dex {
+        deps.api.addr_validate(router)?;
+        deps.api.addr_validate(factory)?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn create_htlc_with_swap(
     deps: DepsMut,
     env: Env,
@@ -269,12 +583,15 @@ fn create_htlc_with_swap(
     target_chain: String,
     target_address: String,
     swap_params: SwapParams,
+    preimage_length: Option<u32>,
+    transferable: bool,
 ) -> Result<Response, ContractError> {
     // Validate swap params
     dex::validate_swap_params(&swap_params)?;
     
     // Validate inputs
     let receiver_addr = deps.api.addr_validate(&receiver)?;
+    let preimage_length = validate_preimage_length(preimage_length)?;
     
     if info.funds.is_empty() {
         return Err(ContractError::InvalidAmount {});
@@ -307,15 +624,53 @@ fn create_htlc_with_swap(
     
     // Estimate swap output
     let token_in = &info.funds[0]; // Assume single token deposit
-    let estimate = dex::estimate_swap(
-        deps.as_ref(),
-        token_in.clone(),
-        swap_params.routes.clone(),
-    )?;
+    let adapter = dex::adapter(&config.protocol_config.swap.dex)?;
+    dex::validate_routes(&*adapter, &swap_params.routes)?;
+    #[cfg(feature = "osmosis")]
+    check_twap_deviation(deps.as_ref(), &env, &config.protocol_config, &token_in.denom, &swap_params.routes)?;
+    let (estimated_input, estimated_output, price_impact) = match swap_params.max_input_amount {
+        #[cfg(not(feature = "osmosis"))]
+        Some(_) => return Err(ContractError::SwapsUnsupported {}),
+        #[cfg(feature = "osmosis")]
+        Some(max_input_amount) => {
+            ensure_exact_out_supported(&config.protocol_config)?;
+            let estimate = dex::estimate_swap_exact_out(
+                deps.as_ref(),
+                token_in.denom.clone(),
+                swap_params.min_output_amount,
+                swap_params.routes.clone(),
+            )?;
+            
+            // The required input must fit both the user's maximum and the deposit
+            if estimate.token_in_amount > max_input_amount.min(token_in.amount) {
+                return Err(ContractError::ExcessiveInputAmount {});
+            }
+            
+            (estimate.token_in_amount, swap_params.min_output_amount, estimate.price_impact)
+        }
+        None => {
+            let estimate = dex::estimate_swap_with(
+                deps.as_ref(),
+                &*adapter,
+                token_in.clone(),
+                swap_params.routes.clone(),
+            )?;
+            
+            // Check if estimated output meets minimum requirement
+            if estimate.token_out_amount < swap_params.min_output_amount {
+                return Err(ContractError::InsufficientOutputAmount {});
+            }
+            
+            (token_in.amount, estimate.token_out_amount, estimate.price_impact)
+        }
+    };
     
-    // Check if estimated output meets minimum requirement
-    if estimate.token_out_amount < swap_params.min_output_amount {
-        return Err(ContractError::InsufficientOutputAmount {});
+    let max_price_impact = config.protocol_config.swap.max_price_impact;
+    if price_impact > max_price_impact {
+        return Err(ContractError::PriceImpactTooHigh {
+            impact: price_impact,
+            max: max_price_impact,
+        });
     }
     
     // Generate HTLC ID
@@ -328,6 +683,7 @@ fn create_htlc_with_swap(
         receiver: receiver_addr.clone(),
         amount: info.funds.clone(),
         hashlock: hashlock.clone(),
+        preimage_length,
         timelock,
         withdrawn: false,
         refunded: false,
@@ -335,23 +691,46 @@ fn create_htlc_with_swap(
         target_address: target_address.clone(),
         swap_params: Some(swap_params.clone()),
         swap_executed: false,
+        creation_quote: Some(SwapQuote {
+            token_in_amount: estimated_input,
+            token_out_amount: estimated_output,
+            min_output_amount: swap_params.min_output_amount,
+            price_impact,
+            quoted_at: env.block.time.seconds(),
+        }),
+        execution_quote: None,
+        secret: None,
+        claim_token: transferable,
+        ibc_origin: None,
     };
     
     // Save HTLC
     HTLCS.save(deps.storage, &htlc_id, &htlc)?;
+    TIMELOCK_INDEX.save(deps.storage, (timelock, &htlc_id), &Empty {})?;
     HTLC_COUNT.save(deps.storage, &(count + 1))?;
     
-    Ok(Response::new()
+    let mut response = Response::new()
         .add_attribute("method", "create_htlc_with_swap")
         .add_attribute("htlc_id", &htlc_id)
         .add_attribute("sender", info.sender)
-        .add_attribute("receiver", receiver_addr)
+        .add_attribute("receiver", receiver_addr.clone())
         .add_attribute("hashlock", hashlock)
         .add_attribute("timelock", timelock.to_string())
         .add_attribute("target_chain", target_chain)
         .add_attribute("target_address", target_address)
-        .add_attribute("estimated_output", estimate.token_out_amount.to_string())
-        .add_attribute("price_impact", estimate.price_impact.to_string()))
+        .add_attribute("estimated_output", estimated_output.to_string())
+        .add_attribute("price_impact", price_impact.to_string());
+    
+    if swap_params.is_exact_out() {
+        response = response.add_attribute("estimated_input", estimated_input.to_string());
+    }
+    
+    if transferable {
+        claim_token::mint(deps.storage, &receiver_addr, &htlc_id)?;
+        response = response.add_attribute("claim_token_id", &htlc_id);
+    }
+    
+    Ok(response)
 }
 
 fn execute_swap_and_lock(
@@ -375,82 +754,383 @@ fn execute_swap_and_lock(
         return Err(ContractError::SwapAlreadyExecuted {});
     }
     
+    // Closed HTLCs no longer hold the funds, and expired ones must stay refundable as deposited
+    if htlc.withdrawn {
+        return Err(ContractError::AlreadyWithdrawn {});
+    }
+    
+    if htlc.refunded {
+        return Err(ContractError::AlreadyRefunded {});
+    }
+    
+    if env.block.time.seconds() >= htlc.timelock {
+        return Err(ContractError::TimelockExpired {});
+    }
+    
+    let swap_params = effective_swap_params(&htlc, &info, swap_params)?;
+    
     // Validate swap params
     dex::validate_swap_params(&swap_params)?;
-    
-    // Build swap message
-    let swap_msg = build_osmosis_swap_msg(
-        &htlc.amount[0], // Assume single token
+    let adapter = dex::adapter(&config.protocol_config.swap.dex)?;
+    dex::validate_routes(&*adapter, &swap_params.routes)?;
+    #[cfg(feature = "osmosis")]
+    check_twap_deviation(
+        deps.as_ref(),
+        &env,
+        &config.protocol_config,
+        &htlc.amount[0].denom,
         &swap_params.routes,
-        swap_params.min_output_amount,
-        env.contract.address.to_string(),
     )?;
     
+    // Re-quote at execution, as the creation quote may be stale by now.
+    // Exact-out swaps reply with the input spent so the rest can be refunded,
+    // exact-in swaps reply to lock the output actually received.
+    let token_in = htlc.amount[0].clone(); // Assume single token
+    let (swap_msg, execution_quote) = match swap_params.max_input_amount {
+        #[cfg(not(feature = "osmosis"))]
+        Some(_) => return Err(ContractError::SwapsUnsupported {}),
+        #[cfg(feature = "osmosis")]
+        Some(max_input_amount) => {
+            ensure_exact_out_supported(&config.protocol_config)?;
+            let max_input_amount = max_input_amount.min(token_in.amount);
+            let estimate = dex::estimate_swap_exact_out(
+                deps.as_ref(),
+                token_in.denom.clone(),
+                swap_params.min_output_amount,
+                swap_params.routes.clone(),
+            )?;
+            
+            if estimate.token_in_amount > max_input_amount {
+                return Err(ContractError::ExcessiveInputAmount {});
+            }
+            
+            let msg = build_osmosis_swap_exact_out_msg(
+                &token_in.denom,
+                max_input_amount,
+                &swap_params.routes,
+                swap_params.min_output_amount,
+                env.contract.address.to_string(),
+            )?;
+            PENDING_SWAP.save(deps.storage, &htlc_id)?;
+            
+            let quote = SwapQuote {
+                token_in_amount: estimate.token_in_amount,
+                token_out_amount: swap_params.min_output_amount,
+                min_output_amount: swap_params.min_output_amount,
+                price_impact: estimate.price_impact,
+                quoted_at: env.block.time.seconds(),
+            };
+            (SubMsg::reply_on_success(msg, SWAP_EXACT_OUT_REPLY_ID), quote)
+        }
+        None => {
+            let estimate = dex::estimate_swap_with(
+                deps.as_ref(),
+                &*adapter,
+                token_in.clone(),
+                swap_params.routes.clone(),
+            )?;
+            
+            // The tolerance applies to the fresh quote, bounded below by the user's floor
+            let effective_min = dex::calculate_min_output_with_slippage(
+                estimate.token_out_amount,
+                swap_params.slippage_tolerance,
+            )?;
+            if effective_min < swap_params.min_output_amount {
+                return Err(ContractError::SlippageFloorBreached {
+                    effective_min,
+                    floor: swap_params.min_output_amount,
+                });
+            }
+            
+            let msg = adapter.swap_msg(
+                env.contract.address.as_str(),
+                &token_in,
+                &swap_params.routes,
+                effective_min,
+            )?;
+            
+            // Not every DEX reports the output amount, so the reply measures the balance change
+            let token_out_denom = swap_params
+                .routes
+                .last()
+                .map(|r| r.token_out_denom.clone())
+                .ok_or(ContractError::HtlcNotFound {})?;
+            let balance = deps.querier.query_balance(&env.contract.address, token_out_denom)?;
+            PENDING_SWAP.save(deps.storage, &htlc_id)?;
+            PENDING_SWAP_BALANCE.save(deps.storage, &balance.amount)?;
+            
+            let quote = SwapQuote {
+                token_in_amount: token_in.amount,
+                token_out_amount: estimate.token_out_amount,
+                min_output_amount: effective_min,
+                price_impact: estimate.price_impact,
+                quoted_at: env.block.time.seconds(),
+            };
+            (SubMsg::reply_on_success(msg, SWAP_EXACT_IN_REPLY_ID), quote)
+        }
+    };
+    
     // Update HTLC state
     htlc.swap_executed = true;
     htlc.swap_params = Some(swap_params);
+    htlc.execution_quote = Some(execution_quote.clone());
     HTLCS.save(deps.storage, &htlc_id, &htlc)?;
     
     Ok(Response::new()
-        .add_message(swap_msg)
+        .add_submessage(swap_msg)
         .add_attribute("method", "execute_swap_and_lock")
-        .add_attribute("htlc_id", htlc_id))
+        .add_attribute("htlc_id", htlc_id)
+        .add_attribute("estimated_output", execution_quote.token_out_amount.to_string())
+        .add_attribute("min_output", execution_quote.min_output_amount.to_string()))
+}
+
+/// Swap parameters to execute with. The ones stored at creation carry the user's
+/// protection, so callers may only tighten them: the routes must match, the floor can
+/// only be raised and the maximum input only lowered. HTLCs created without a swap
+/// can only be given parameters by their sender.
+fn effective_swap_params(
+    htlc: &Htlc,
+    info: &MessageInfo,
+    swap_params: SwapParams,
+) -> Result<SwapParams, ContractError> {
+    let stored = match &htlc.swap_params {
+        Some(stored) => stored,
+        None if info.sender == htlc.sender => return Ok(swap_params),
+        None => return Err(ContractError::Unauthorized {}),
+    };
+    
+    if swap_params.routes != stored.routes {
+        return Err(StdError::generic_err("Swap routes differ from those set at creation").into());
+    }
+    
+    if swap_params.min_output_amount < stored.min_output_amount {
+        return Err(ContractError::MinOutputBelowFloor {
+            min_output: swap_params.min_output_amount,
+            floor: stored.min_output_amount,
+        });
+    }
+    
+    let max_input_amount = stored
+        .max_input_amount
+        .map(|max| swap_params.max_input_amount.map_or(max, |requested| requested.min(max)));
+    
+    Ok(SwapParams {
+        routes: stored.routes.clone(),
+        min_output_amount: swap_params.min_output_amount,
+        slippage_tolerance: swap_params.slippage_tolerance,
+        max_input_amount,
+    })
+}
+
+/// Settle an exact-out swap: refund unspent input to the sender and lock the bought amount
+#[cfg(feature = "osmosis")]
+fn swap_exact_out_reply(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let response: MsgSwapExactAmountOutResponse = msg.result.try_into()?;
+    let htlc_id = PENDING_SWAP.load(deps.storage)?;
+    PENDING_SWAP.remove(deps.storage);
+    
+    let mut htlc = HTLCS.load(deps.storage, &htlc_id)?;
+    let swap_params = htlc.swap_params.clone().ok_or(ContractError::HtlcNotFound {})?;
+    let token_out_denom = swap_params
+        .routes
+        .last()
+        .map(|r| r.token_out_denom.clone())
+        .ok_or(ContractError::HtlcNotFound {})?;
+    
+    let token_in_spent = Uint128::from_str(&response.token_in_amount)?;
+    let token_in = htlc.amount[0].clone();
+    let leftover = token_in.amount.checked_sub(token_in_spent).map_err(StdError::from)?;
+    
+    htlc.amount = vec![Coin {
+        denom: token_out_denom,
+        amount: swap_params.min_output_amount,
+    }];
+    HTLCS.save(deps.storage, &htlc_id, &htlc)?;
+    
+    let mut response = Response::new()
+        .add_attribute("method", "swap_exact_out_reply")
+        .add_attribute("htlc_id", htlc_id)
+        .add_attribute("token_in_spent", token_in_spent.to_string())
+        .add_attribute("refunded", leftover.to_string());
+    
+    if !leftover.is_zero() {
+        response = response.add_message(BankMsg::Send {
+            to_address: htlc.sender.to_string(),
+            amount: vec![Coin {
+                denom: token_in.denom,
+                amount: leftover,
+            }],
+        });
+    }
+    
+    Ok(response)
+}
+
+/// Settle an exact-in swap: lock the output received in place of the spent input
+fn swap_exact_in_reply(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let htlc_id = PENDING_SWAP.load(deps.storage)?;
+    let balance_before = PENDING_SWAP_BALANCE.load(deps.storage)?;
+    PENDING_SWAP.remove(deps.storage);
+    PENDING_SWAP_BALANCE.remove(deps.storage);
+    
+    let mut htlc = HTLCS.load(deps.storage, &htlc_id)?;
+    let token_out_denom = htlc
+        .swap_params
+        .as_ref()
+        .and_then(|p| p.routes.last())
+        .map(|r| r.token_out_denom.clone())
+        .ok_or(ContractError::HtlcNotFound {})?;
+    
+    let balance = deps.querier.query_balance(&env.contract.address, &token_out_denom)?;
+    let token_out_amount = balance.amount.checked_sub(balance_before).map_err(StdError::from)?;
+    
+    htlc.amount = vec![Coin {
+        denom: token_out_denom,
+        amount: token_out_amount,
+    }];
+    HTLCS.save(deps.storage, &htlc_id, &htlc)?;
+    
+    Ok(Response::new()
+        .add_attribute("method", "swap_exact_in_reply")
+        .add_attribute("htlc_id", htlc_id)
+        .add_attribute("token_out_amount", token_out_amount.to_string()))
+}
+
+/// Reject routes where a pool's spot price has moved away from its TWAP, which
+/// indicates the price was manipulated within the current block.
+/// Only Osmosis pools expose an arithmetic TWAP, so other DEXes are not checked.
+#[cfg(feature = "osmosis")]
+fn check_twap_deviation(
+    deps: Deps,
+    env: &Env,
+    protocol_config: &ProtocolConfig,
+    token_in_denom: &str,
+    routes: &[SwapRoute],
+) -> Result<(), ContractError> {
+    if protocol_config.swap.dex != DexConfig::Osmosis {
+        return Ok(());
+    }
+    
+    let max_deviation = protocol_config.swap.max_twap_deviation;
+    let prices = dex::query_route_twap_prices(
+        deps,
+        env,
+        token_in_denom,
+        routes,
+        protocol_config.swap.twap_window,
+    )?;
+    
+    if let Some(price) = prices.into_iter().find(|p| p.deviation > max_deviation) {
+        return Err(ContractError::TwapDeviationTooHigh {
+            pool_id: price.pool_id,
+            deviation: price.deviation,
+            max: max_deviation,
+        });
+    }
+    
+    Ok(())
+}
+
+/// Exact-out swaps go through Osmosis' MsgSwapExactAmountOut, which other DEXes lack
+#[cfg(feature = "osmosis")]
+fn ensure_exact_out_supported(protocol_config: &ProtocolConfig) -> Result<(), ContractError> {
+    if protocol_config.swap.dex != DexConfig::Osmosis {
+        return Err(StdError::generic_err("Exact-out swaps are only supported on Osmosis").into());
+    }
+    Ok(())
 }
 
-fn build_osmosis_swap_msg(
-    token_in: &Coin,
+#[cfg(feature = "osmosis")]
+fn build_osmosis_swap_exact_out_msg(
+    token_in_denom: &str,
+    max_input_amount: Uint128,
     routes: &[SwapRoute],
-    min_output_amount: Uint128,
+    token_out_amount: Uint128,
     sender: String,
 ) -> StdResult<CosmosMsg> {
-    use osmosis_std::types::osmosis::poolmanager::v1beta1::SwapAmountInRoute;
+    let token_out_denom = routes
+        .last()
+        .map(|r| r.token_out_denom.clone())
+        .ok_or_else(|| StdError::generic_err("Swap routes cannot be empty"))?;
     
-    let osmo_routes: Vec<SwapAmountInRoute> = routes
-        .iter()
-        .map(|r| SwapAmountInRoute {
-            pool_id: r.pool_id,
-            token_out_denom: r.token_out_denom.clone(),
-        })
-        .collect();
-    
-    let msg = MsgSwapExactAmountIn {
+    let msg = MsgSwapExactAmountOut {
         sender,
-        routes: osmo_routes,
-        token_in: Some(osmosis_std::types::cosmos::base::v1beta1::Coin {
-            denom: token_in.denom.clone(),
-            amount: token_in.amount.to_string(),
+        routes: dex::to_amount_out_routes(token_in_denom, routes),
+        token_in_max_amount: max_input_amount.to_string(),
+        token_out: Some(osmosis_std::types::cosmos::base::v1beta1::Coin {
+            denom: token_out_denom,
+            amount: token_out_amount.to_string(),
         }),
-        token_out_min_amount: min_output_amount.to_string(),
     };
     
     Ok(msg.into())
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetHtlc { htlc_id } => to_json_binary(&query_htlc(deps, htlc_id)?),
+        QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
         QueryMsg::ListHtlcs { start_after, limit } => {
             to_json_binary(&query_list_htlcs(deps, start_after, limit)?)
         }
+        QueryMsg::ListExpiring { before, start_after, limit } => {
+            to_json_binary(&query_list_expiring(deps, env, before, start_after, limit)?)
+        }
+        QueryMsg::ListRefundable { sender, start_after, limit } => {
+            to_json_binary(&query_list_refundable(deps, env, sender, start_after, limit)?)
+        }
+        #[cfg(feature = "osmosis")]
         QueryMsg::QuerySpotPrice { pool_id, base_denom, quote_denom } => {
             to_json_binary(&dex::query_spot_price(deps, pool_id, base_denom, quote_denom)?)
         }
+        #[cfg(feature = "osmosis")]
+        QueryMsg::QueryTwapPrice { pool_id, base_denom, quote_denom, window } => {
+            let window = match window {
+                Some(window) => window,
+                None => CONFIG.load(deps.storage)?.protocol_config.swap.twap_window,
+            };
+            to_json_binary(&dex::query_twap_price(deps, &env, pool_id, base_denom, quote_denom, window)?)
+        }
+        QueryMsg::GetSecret { hashlock } => to_json_binary(&query_secret(deps, hashlock)?),
         QueryMsg::EstimateSwap { token_in, routes } => {
-            to_json_binary(&dex::estimate_swap(deps, token_in, routes)?)
+            let config = CONFIG.load(deps.storage)?;
+            let adapter = dex::adapter(&config.protocol_config.swap.dex)
+                .map_err(|e| StdError::generic_err(e.to_string()))?;
+            to_json_binary(&dex::estimate_swap_with(deps, &*adapter, token_in, routes)?)
+        }
+        #[cfg(feature = "osmosis")]
+        QueryMsg::EstimateSwapExactOut { token_in_denom, token_out_amount, routes } => {
+            to_json_binary(&dex::estimate_swap_exact_out(deps, token_in_denom, token_out_amount, routes)?)
+        }
+        #[cfg(not(feature = "osmosis"))]
+        QueryMsg::QuerySpotPrice { .. }
+        | QueryMsg::QueryTwapPrice { .. }
+        | QueryMsg::EstimateSwapExactOut { .. } => {
+            Err(StdError::generic_err(ContractError::SwapsUnsupported {}.to_string()))
         }
+        QueryMsg::OwnerOf { token_id, .. } => {
+            to_json_binary(&claim_token::query_owner_of(deps, token_id)?)
+        }
+        QueryMsg::NftInfo { token_id } => {
+            to_json_binary(&claim_token::query_nft_info(deps, token_id)?)
+        }
+        QueryMsg::NumTokens {} => to_json_binary(&claim_token::query_num_tokens(deps)?),
+        QueryMsg::Tokens { owner, start_after, limit } => {
+            to_json_binary(&claim_token::query_tokens(deps, owner, start_after, limit)?)
+        }
+        QueryMsg::ContractInfo {} => to_json_binary(&claim_token::query_contract_info()),
     }
 }
 
-fn query_htlc(deps: Deps, htlc_id: String) -> StdResult<HtlcResponse> {
-    let htlc = HTLCS.load(deps.storage, &htlc_id)?;
-
-    Ok(HtlcResponse {
-        id: htlc_id,
+pub(crate) fn htlc_response(id: String, htlc: Htlc) -> HtlcResponse {
+    HtlcResponse {
+        id,
         sender: htlc.sender.to_string(),
         receiver: htlc.receiver.to_string(),
         amount: htlc.amount,
         hashlock: htlc.hashlock,
+        preimage_length: htlc.preimage_length,
         timelock: htlc.timelock,
         withdrawn: htlc.withdrawn,
         refunded: htlc.refunded,
@@ -458,9 +1138,31 @@ fn query_htlc(deps: Deps, htlc_id: String) -> StdResult<HtlcResponse> {
         target_address: htlc.target_address,
         swap_params: htlc.swap_params,
         swap_executed: htlc.swap_executed,
+        creation_quote: htlc.creation_quote,
+        execution_quote: htlc.execution_quote,
+        secret: htlc.secret,
+        claim_token: htlc.claim_token,
+        ibc_origin: htlc.ibc_origin,
+    }
+}
+
+fn query_config(deps: Deps) -> StdResult<ConfigResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    Ok(ConfigResponse {
+        config: config.protocol_config,
     })
 }
 
+fn query_htlc(deps: Deps, htlc_id: String) -> StdResult<HtlcResponse> {
+    let htlc = HTLCS.load(deps.storage, &htlc_id)?;
+    Ok(htlc_response(htlc_id, htlc))
+}
+
+fn query_secret(deps: Deps, hashlock: String) -> StdResult<SecretResponse> {
+    let secret = SECRETS.may_load(deps.storage, &hashlock)?;
+    Ok(SecretResponse { hashlock, secret })
+}
+
 fn query_list_htlcs(
     deps: Deps,
     start_after: Option<String>,
@@ -475,32 +1177,96 @@ fn query_list_htlcs(
         .take(limit as usize)
         .map(|item| {
             let (id, htlc) = item?;
-            Ok(HtlcResponse {
-                id,
-                sender: htlc.sender.to_string(),
-                receiver: htlc.receiver.to_string(),
-                amount: htlc.amount,
-                hashlock: htlc.hashlock,
-                timelock: htlc.timelock,
-                withdrawn: htlc.withdrawn,
-                refunded: htlc.refunded,
-                target_chain: htlc.target_chain,
-                target_address: htlc.target_address,
-                swap_params: htlc.swap_params,
-                swap_executed: htlc.swap_executed,
-            })
+            Ok(htlc_response(id, htlc))
         })
         .collect();
 
     Ok(ListHtlcsResponse { htlcs: htlcs? })
 }
 
+fn query_list_expiring(
+    deps: Deps,
+    env: Env,
+    before: u64,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListHtlcExpiryResponse> {
+    let limit = limit.unwrap_or(10).min(100);
+    let now = env.block.time.seconds();
+
+    // Resume after the given HTLC, otherwise start at the first unexpired timelock
+    let start_key = match &start_after {
+        Some(id) => (HTLCS.load(deps.storage, id)?.timelock, id.as_str()),
+        None => (now, ""),
+    };
+    let start = Some(Bound::exclusive(start_key));
+    let end = Some(Bound::exclusive((before.saturating_add(1), "")));
+
+    let htlcs: StdResult<Vec<HtlcExpiryResponse>> = TIMELOCK_INDEX
+        .keys(deps.storage, start, end, Order::Ascending)
+        .filter(|item| !matches!(item, Ok((timelock, _)) if *timelock <= now))
+        .take(limit as usize)
+        .map(|item| {
+            let (_, id) = item?;
+            htlc_expiry_response(deps, id, now)
+        })
+        .collect();
+
+    Ok(ListHtlcExpiryResponse { htlcs: htlcs? })
+}
+
+fn query_list_refundable(
+    deps: Deps,
+    env: Env,
+    sender: Option<String>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ListHtlcExpiryResponse> {
+    let limit = limit.unwrap_or(10).min(100);
+    let now = env.block.time.seconds();
+    let sender = sender.map(|s| deps.api.addr_validate(&s)).transpose()?;
+
+    let start_key = match &start_after {
+        Some(id) => Some((HTLCS.load(deps.storage, id)?.timelock, id.as_str())),
+        None => None,
+    };
+    let start = start_key.map(Bound::exclusive);
+    // Refunds are allowed once the block time reaches the timelock
+    let end = Some(Bound::exclusive((now.saturating_add(1), "")));
+
+    let htlcs: StdResult<Vec<HtlcExpiryResponse>> = TIMELOCK_INDEX
+        .keys(deps.storage, start, end, Order::Ascending)
+        .map(|item| {
+            let (_, id) = item?;
+            htlc_expiry_response(deps, id, now)
+        })
+        .filter(|item| match (item, &sender) {
+            (Ok(entry), Some(sender)) => entry.htlc.sender == sender.as_str(),
+            _ => true,
+        })
+        .take(limit as usize)
+        .collect();
+
+    Ok(ListHtlcExpiryResponse { htlcs: htlcs? })
+}
+
+fn htlc_expiry_response(deps: Deps, id: String, now: u64) -> StdResult<HtlcExpiryResponse> {
+    let htlc = HTLCS.load(deps.storage, &id)?;
+    let seconds_remaining = htlc.timelock as i64 - now as i64;
+    Ok(HtlcExpiryResponse {
+        htlc: htlc_response(id, htlc),
+        seconds_remaining,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
     use cosmwasm_std::{coins, Decimal, Uint128};
-    use crate::dex::{SwapParams, SwapRoute};
+    use crate::dex::{Dex, SwapParams, SwapRoute};
+    #[cfg(feature = "osmosis")]
+    use osmosis_std::types::osmosis::poolmanager::v1beta1::MsgSwapExactAmountIn;
 
     #[test]
     fn test_instantiate() {
@@ -536,6 +1302,8 @@ mod tests {
             timelock: env.block.time.seconds() + 3600,
             target_chain: "cosmoshub-4".to_string(),
             target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
         };
 
         let res = execute(deps.as_mut(), env.clone(), sender_info, msg).unwrap();
@@ -544,12 +1312,13 @@ mod tests {
         // Verify HTLC was created properly
         let htlc = HTLCS.load(&deps.storage, "htlc_0").unwrap();
         assert_eq!(htlc.swap_params, None);
-        assert_eq!(htlc.swap_executed, false);
+        assert!(!htlc.swap_executed);
     }
     
     #[test]
+    #[cfg(feature = "osmosis")]
     fn test_create_htlc_with_swap() {
-        use crate::test_helpers::test_helpers::mock_dependencies_with_osmosis;
+        use crate::test_helpers::mock_dependencies_with_osmosis;
         
         let mut deps = mock_dependencies_with_osmosis();
         let env = mock_env();
@@ -570,9 +1339,11 @@ mod tests {
             routes: vec![SwapRoute {
                 pool_id: 1,
                 token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
             }],
             min_output_amount: Uint128::new(90),
             slippage_tolerance: Decimal::percent(1),
+            max_input_amount: None,
         };
         
         let msg = ExecuteMsg::CreateHtlcWithSwap {
@@ -582,6 +1353,8 @@ mod tests {
             target_chain: "cosmoshub-4".to_string(),
             target_address: "cosmos1abc...".to_string(),
             swap_params: swap_params.clone(),
+            preimage_length: None,
+            transferable: None,
         };
         
         // Now with proper Osmosis query support, this should succeed
@@ -602,7 +1375,261 @@ mod tests {
         assert_eq!(htlc.amount, coins(100, "uatom"));
         assert!(htlc.swap_params.is_some());
         assert_eq!(htlc.swap_params.unwrap(), swap_params);
-        assert_eq!(htlc.swap_executed, false);
+        assert!(!htlc.swap_executed);
+    }
+
+    #[test]
+    #[cfg(feature = "osmosis")]
+    fn test_execute_swap_requotes_with_slippage() {
+        use crate::test_helpers::mock_dependencies_with_osmosis;
+        
+        let mut deps = mock_dependencies_with_osmosis();
+        let env = mock_env();
+        
+        let msg = InstantiateMsg { admin: None };
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), msg).unwrap();
+        
+        let mut swap_params = SwapParams {
+            routes: vec![SwapRoute {
+                pool_id: 1,
+                token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
+            }],
+            min_output_amount: Uint128::new(90),
+            slippage_tolerance: Decimal::percent(10),
+            max_input_amount: None,
+        };
+        
+        let msg = ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            swap_params: swap_params.clone(),
+            preimage_length: None,
+            transferable: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        
+        // The creation quote is recorded against the user's floor
+        let htlc = HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        let creation_quote = htlc.creation_quote.unwrap();
+        assert_eq!(creation_quote.token_out_amount, Uint128::new(95));
+        assert_eq!(creation_quote.min_output_amount, Uint128::new(90));
+        assert_eq!(htlc.execution_quote, None);
+        
+        // 10% below the fresh 95 quote is 85, under the 90 floor
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_0".to_string(),
+            swap_params: swap_params.clone(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("sender", &[]), msg).unwrap_err();
+        match err {
+            ContractError::SlippageFloorBreached { effective_min, floor } => {
+                assert_eq!(effective_min, Uint128::new(85));
+                assert_eq!(floor, Uint128::new(90));
+            }
+            _ => panic!("Expected SlippageFloorBreached error, got {:?}", err),
+        }
+        
+        // 1% below the fresh quote clears the floor and becomes the swap minimum
+        swap_params.slippage_tolerance = Decimal::percent(1);
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_0".to_string(),
+            swap_params,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("sender", &[]), msg).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Stargate { value, .. } => {
+                let swap = MsgSwapExactAmountIn::try_from(value.clone()).unwrap();
+                assert_eq!(swap.token_out_min_amount, "94");
+            }
+            _ => panic!("Expected swap message"),
+        }
+        
+        let htlc = HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        let execution_quote = htlc.execution_quote.unwrap();
+        assert_eq!(execution_quote.token_out_amount, Uint128::new(95));
+        assert_eq!(execution_quote.min_output_amount, Uint128::new(94));
+        assert_eq!(execution_quote.quoted_at, env.block.time.seconds());
+    }
+
+    #[test]
+    #[cfg(feature = "osmosis")]
+    fn test_execute_swap_keeps_creation_floor() {
+        use crate::test_helpers::mock_dependencies_with_osmosis;
+        
+        let mut deps = mock_dependencies_with_osmosis();
+        let env = mock_env();
+        
+        let msg = InstantiateMsg { admin: None };
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), msg).unwrap();
+        
+        let swap_params = SwapParams {
+            routes: vec![SwapRoute {
+                pool_id: 1,
+                token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
+            }],
+            min_output_amount: Uint128::new(90),
+            slippage_tolerance: Decimal::percent(1),
+            max_input_amount: None,
+        };
+        let msg = ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            swap_params: swap_params.clone(),
+            preimage_length: None,
+            transferable: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        
+        // The admin cannot lower the floor the user set at creation
+        let mut weakened = swap_params.clone();
+        weakened.min_output_amount = Uint128::new(1);
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_0".to_string(),
+            swap_params: weakened,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), msg).unwrap_err();
+        match err {
+            ContractError::MinOutputBelowFloor { min_output, floor } => {
+                assert_eq!(min_output, Uint128::new(1));
+                assert_eq!(floor, Uint128::new(90));
+            }
+            _ => panic!("Expected MinOutputBelowFloor error, got {:?}", err),
+        }
+        
+        // Nor reroute the swap
+        let mut rerouted = swap_params.clone();
+        rerouted.routes[0].pool_id = 2;
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_0".to_string(),
+            swap_params: rerouted,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), msg).unwrap_err();
+        
+        let htlc = HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        assert!(!htlc.swap_executed);
+        assert_eq!(htlc.swap_params, Some(swap_params.clone()));
+        
+        // Executing with the stored floor goes through and keeps it
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_0".to_string(),
+            swap_params: swap_params.clone(),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), msg).unwrap();
+        let htlc = HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        assert_eq!(htlc.swap_params, Some(swap_params.clone()));
+        
+        // Only the sender may attach swap parameters to an HTLC created without them
+        let msg = ExecuteMsg::CreateHtlc {
+            receiver: "receiver".to_string(),
+            hashlock: "b".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_1".to_string(),
+            swap_params,
+        };
+        let err = execute(deps.as_mut(), env, mock_info("admin", &[]), msg).unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("Expected Unauthorized error, got {:?}", err),
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "osmosis"))]
+    fn test_swaps_unsupported_without_osmosis() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        
+        let msg = InstantiateMsg { admin: None };
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), msg).unwrap();
+        
+        let mut hasher = Sha256::new();
+        hasher.update(b"mysecret");
+        let hashlock = hex::encode(hasher.finalize());
+        
+        let swap_params = SwapParams {
+            routes: vec![SwapRoute {
+                pool_id: 1,
+                token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
+            }],
+            min_output_amount: Uint128::new(90),
+            slippage_tolerance: Decimal::percent(1),
+            max_input_amount: None,
+        };
+        
+        // Swaps on the default Osmosis DEX are rejected
+        let msg = ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: hashlock.clone(),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            swap_params: swap_params.clone(),
+            preimage_length: None,
+            transferable: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg)
+            .unwrap_err();
+        assert!(matches!(err, ContractError::SwapsUnsupported {}));
+        
+        let err = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::EstimateSwap {
+                token_in: cosmwasm_std::Coin::new(100, "uatom"),
+                routes: swap_params.routes.clone(),
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("Swaps unsupported"));
+        
+        // So is creation through ibc-hooks
+        let msg = ExecuteMsg::CreateHtlcFromIbc {
+            channel: "channel-0".to_string(),
+            original_sender: "cosmos1sender".to_string(),
+            receiver: "receiver".to_string(),
+            hashlock: hashlock.clone(),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg)
+            .unwrap_err();
+        assert!(matches!(err, ContractError::IbcHooksUnsupported {}));
+        
+        // Plain HTLCs still work
+        let msg = ExecuteMsg::CreateHtlc {
+            receiver: "receiver".to_string(),
+            hashlock,
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: Some(8),
+            transferable: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        
+        let msg = ExecuteMsg::Withdraw {
+            htlc_id: "htlc_0".to_string(),
+            secret: hex::encode(b"mysecret"),
+        };
+        execute(deps.as_mut(), env, mock_info("receiver", &[]), msg).unwrap();
     }
 
     #[test]
@@ -617,7 +1644,7 @@ mod tests {
         
         // Create HTLC
         let sender_info = mock_info("sender", &coins(100, "uatom"));
-        let secret = b"mysecret";
+        let secret = [7u8; 32];
         let mut hasher = Sha256::new();
         hasher.update(secret);
         let hashlock = hex::encode(hasher.finalize());
@@ -628,6 +1655,8 @@ mod tests {
             timelock: env.block.time.seconds() + 3600,
             target_chain: "cosmoshub-4".to_string(),
             target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
         };
         
         execute(deps.as_mut(), env.clone(), sender_info, msg).unwrap();
@@ -650,6 +1679,54 @@ mod tests {
         assert!(htlc.withdrawn);
     }
 
+    #[test]
+    fn test_withdraw_persists_secret() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("admin", &[]);
+        
+        // Instantiate
+        let msg = InstantiateMsg { admin: None };
+        instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+        
+        // Create HTLC
+        let sender_info = mock_info("sender", &coins(100, "uatom"));
+        let secret = [7u8; 32];
+        let mut hasher = Sha256::new();
+        hasher.update(secret);
+        let hashlock = hex::encode(hasher.finalize());
+        
+        let msg = ExecuteMsg::CreateHtlc {
+            receiver: "receiver".to_string(),
+            hashlock: hashlock.clone(),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
+        };
+        execute(deps.as_mut(), env.clone(), sender_info, msg).unwrap();
+        
+        // Secret is unknown before withdrawal
+        let res = query_secret(deps.as_ref(), hashlock.clone()).unwrap();
+        assert_eq!(res.secret, None);
+        
+        // Withdraw
+        let receiver_info = mock_info("receiver", &[]);
+        let msg = ExecuteMsg::Withdraw {
+            htlc_id: "htlc_0".to_string(),
+            secret: hex::encode(secret),
+        };
+        execute(deps.as_mut(), env.clone(), receiver_info, msg).unwrap();
+        
+        // Secret is recoverable from state by hashlock and by HTLC id
+        let res = query_secret(deps.as_ref(), hashlock.clone()).unwrap();
+        assert_eq!(res.secret, Some(hex::encode(secret)));
+        
+        let htlc = query_htlc(deps.as_ref(), "htlc_0".to_string()).unwrap();
+        assert_eq!(htlc.secret, Some(hex::encode(secret)));
+    }
+
     #[test]
     fn test_claim_htlc_wrong_secret() {
         let mut deps = mock_dependencies();
@@ -662,7 +1739,7 @@ mod tests {
         
         // Create HTLC
         let sender_info = mock_info("sender", &coins(100, "uatom"));
-        let secret = b"mysecret";
+        let secret = [7u8; 32];
         let mut hasher = Sha256::new();
         hasher.update(secret);
         let hashlock = hex::encode(hasher.finalize());
@@ -673,6 +1750,8 @@ mod tests {
             timelock: env.block.time.seconds() + 3600,
             target_chain: "cosmoshub-4".to_string(),
             target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
         };
         
         execute(deps.as_mut(), env.clone(), sender_info, msg).unwrap();
@@ -684,7 +1763,7 @@ mod tests {
         let receiver_info = mock_info("receiver", &[]);
         let msg = ExecuteMsg::Withdraw {
             htlc_id,
-            secret: hex::encode(b"wrongsecret"),
+            secret: hex::encode([8u8; 32]),
         };
         
         let err = execute(deps.as_mut(), env, receiver_info, msg).unwrap_err();
@@ -716,6 +1795,8 @@ mod tests {
             timelock: env.block.time.seconds() + 3600,
             target_chain: "cosmoshub-4".to_string(),
             target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
         };
         
         execute(deps.as_mut(), env.clone(), sender_info.clone(), msg).unwrap();
@@ -736,6 +1817,89 @@ mod tests {
         assert!(htlc.refunded);
     }
 
+    #[test]
+    fn test_list_expiring_and_refundable() {
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("admin", &[]);
+        
+        // Instantiate
+        let msg = InstantiateMsg { admin: None };
+        instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+        
+        let mut hasher = Sha256::new();
+        hasher.update(b"mysecret");
+        let hashlock = hex::encode(hasher.finalize());
+        let now = env.block.time.seconds();
+        
+        // Create HTLCs expiring in 1h, 2h and 3h
+        for (sender, offset) in [("alice", 3600u64), ("bob", 7200), ("alice", 10800)] {
+            let msg = ExecuteMsg::CreateHtlc {
+                receiver: "receiver".to_string(),
+                hashlock: hashlock.clone(),
+                timelock: now + offset,
+                target_chain: "cosmoshub-4".to_string(),
+                target_address: "cosmos1abc...".to_string(),
+                preimage_length: None,
+                transferable: None,
+            };
+            execute(deps.as_mut(), env.clone(), mock_info(sender, &coins(100, "uatom")), msg).unwrap();
+        }
+        
+        // Only the first two expire within the next 2 hours
+        let res = query_list_expiring(deps.as_ref(), env.clone(), now + 7200, None, None).unwrap();
+        assert_eq!(res.htlcs.len(), 2);
+        assert_eq!(res.htlcs[0].htlc.id, "htlc_0");
+        assert_eq!(res.htlcs[0].seconds_remaining, 3600);
+        assert_eq!(res.htlcs[1].htlc.id, "htlc_1");
+        
+        // Paging resumes after the given HTLC
+        let res = query_list_expiring(
+            deps.as_ref(),
+            env.clone(),
+            now + 10800,
+            Some("htlc_0".to_string()),
+            Some(1),
+        )
+        .unwrap();
+        assert_eq!(res.htlcs.len(), 1);
+        assert_eq!(res.htlcs[0].htlc.id, "htlc_1");
+        
+        // Nothing is refundable yet
+        let res = query_list_refundable(deps.as_ref(), env.clone(), None, None, None).unwrap();
+        assert!(res.htlcs.is_empty());
+        
+        // Advance past the first two timelocks
+        env.block.time = env.block.time.plus_seconds(7300);
+        
+        let res = query_list_refundable(deps.as_ref(), env.clone(), None, None, None).unwrap();
+        assert_eq!(res.htlcs.len(), 2);
+        assert_eq!(res.htlcs[0].seconds_remaining, -3700);
+        
+        let res = query_list_refundable(
+            deps.as_ref(),
+            env.clone(),
+            Some("alice".to_string()),
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.htlcs.len(), 1);
+        assert_eq!(res.htlcs[0].htlc.id, "htlc_0");
+        
+        // Refunded HTLCs drop out of the index
+        let msg = ExecuteMsg::Refund { htlc_id: "htlc_0".to_string() };
+        execute(deps.as_mut(), env.clone(), mock_info("alice", &[]), msg).unwrap();
+        
+        let res = query_list_refundable(deps.as_ref(), env.clone(), None, None, None).unwrap();
+        assert_eq!(res.htlcs.len(), 1);
+        assert_eq!(res.htlcs[0].htlc.id, "htlc_1");
+        
+        let res = query_list_expiring(deps.as_ref(), env, now + 10800, None, None).unwrap();
+        assert_eq!(res.htlcs.len(), 1);
+        assert_eq!(res.htlcs[0].htlc.id, "htlc_2");
+    }
+
     #[test]
     fn test_refund_htlc_before_timelock() {
         let mut deps = mock_dependencies();
@@ -758,6 +1922,8 @@ mod tests {
             timelock: env.block.time.seconds() + 3600,
             target_chain: "cosmoshub-4".to_string(),
             target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
         };
         
         execute(deps.as_mut(), env.clone(), sender_info.clone(), msg).unwrap();
@@ -775,6 +1941,81 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(feature = "osmosis")]
+    fn test_create_htlc_from_ibc_and_refund() {
+        use cosmwasm_std::{Addr, SubMsgResponse, SubMsgResult};
+        
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        env.contract.address = Addr::unchecked("osmo1htlccontract");
+        let info = mock_info("admin", &[]);
+        
+        // Instantiate
+        let msg = InstantiateMsg { admin: None };
+        instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+        
+        let mut hasher = Sha256::new();
+        hasher.update([7u8; 32]);
+        let hashlock = hex::encode(hasher.finalize());
+        let msg = ExecuteMsg::CreateHtlcFromIbc {
+            channel: "channel-0".to_string(),
+            original_sender: "cosmos1sender".to_string(),
+            receiver: "receiver".to_string(),
+            hashlock,
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+        };
+        
+        // Only the hooks-derived address for this channel and sender may create it
+        let err = execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "ibc/ATOM")), msg.clone())
+            .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("Expected Unauthorized error, got {:?}", err),
+        }
+        
+        let derived = ibc_hooks::derive_intermediate_sender("channel-0", "cosmos1sender", "osmo").unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info(&derived, &coins(100, "ibc/ATOM")), msg).unwrap();
+        
+        let htlc = HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        assert_eq!(htlc.sender, derived);
+        assert_eq!(htlc.ibc_origin.as_ref().unwrap().original_sender, "cosmos1sender");
+        
+        // After expiry anyone can refund, which sends the funds back over the source channel
+        env.block.time = env.block.time.plus_seconds(3601);
+        let msg = ExecuteMsg::Refund { htlc_id: "htlc_0".to_string() };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, IBC_REFUND_REPLY_ID);
+        
+        // The transfer reply records the packet sequence
+        let reply_msg = Reply {
+            id: IBC_REFUND_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(MsgTransferResponse { sequence: 3 }.into()),
+            }),
+        };
+        reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+        assert_eq!(IBC_REFUNDS.load(&deps.storage, ("channel-0", 3)).unwrap(), "htlc_0");
+        
+        // A timed out refund makes the HTLC refundable again
+        let msg = SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCTimeout {
+            channel: "channel-0".to_string(),
+            sequence: 3,
+        });
+        sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        
+        let htlc = HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        assert!(!htlc.refunded);
+        assert!(IBC_REFUNDS.may_load(&deps.storage, ("channel-0", 3)).unwrap().is_none());
+        let res = query_list_refundable(deps.as_ref(), env, None, None, None).unwrap();
+        assert_eq!(res.htlcs.len(), 1);
+    }
+
     #[test]
     fn test_create_htlc_invalid_timelock() {
         let mut deps = mock_dependencies();
@@ -797,6 +2038,8 @@ mod tests {
             timelock: env.block.time.seconds() + 49 * 3600, // > 48 hours
             target_chain: "cosmoshub-4".to_string(),
             target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
         };
         
         let err = execute(deps.as_mut(), env, sender_info, msg).unwrap_err();
@@ -805,4 +2048,79 @@ mod tests {
             _ => panic!("Expected InvalidTimelock error, got {:?}", err),
         }
     }
+
+    #[test]
+    fn test_withdraw_enforces_preimage_length() {
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("admin", &[]);
+        
+        // Instantiate
+        let msg = InstantiateMsg { admin: None };
+        instantiate(deps.as_mut(), env.clone(), info, msg).unwrap();
+        
+        // A short preimage hashes fine but cannot be submitted on Ethereum
+        let short_secret = b"mysecret";
+        let mut hasher = Sha256::new();
+        hasher.update(short_secret);
+        let hashlock = hex::encode(hasher.finalize());
+        
+        let msg = ExecuteMsg::CreateHtlc {
+            receiver: "receiver".to_string(),
+            hashlock: hashlock.clone(),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: None,
+            transferable: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        
+        let htlc = HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        assert_eq!(htlc.preimage_length, DEFAULT_PREIMAGE_LENGTH);
+        
+        let msg = ExecuteMsg::Withdraw {
+            htlc_id: "htlc_0".to_string(),
+            secret: hex::encode(short_secret),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("receiver", &[]), msg).unwrap_err();
+        match err {
+            ContractError::InvalidSecretLength { expected: 32, actual: 8 } => {}
+            _ => panic!("Expected InvalidSecretLength error, got {:?}", err),
+        }
+        
+        // Declaring the length up front makes the same preimage acceptable
+        let msg = ExecuteMsg::CreateHtlc {
+            receiver: "receiver".to_string(),
+            hashlock,
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: Some(8),
+            transferable: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        
+        let msg = ExecuteMsg::Withdraw {
+            htlc_id: "htlc_1".to_string(),
+            secret: hex::encode(short_secret),
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("receiver", &[]), msg).unwrap();
+        
+        // Zero-length preimages are rejected at creation
+        let msg = ExecuteMsg::CreateHtlc {
+            receiver: "receiver".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            preimage_length: Some(0),
+            transferable: None,
+        };
+        let err = execute(deps.as_mut(), env, mock_info("sender", &coins(100, "uatom")), msg).unwrap_err();
+        match err {
+            ContractError::InvalidPreimageLength { length: 0, .. } => {}
+            _ => panic!("Expected InvalidPreimageLength error, got {:?}", err),
+        }
+    }
 }
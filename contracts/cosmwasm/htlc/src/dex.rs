@@ -1,22 +1,183 @@
-use cosmwasm_std::{Coin, Decimal, Deps, StdError, StdResult, Uint128};
+use cosmwasm_std::{Coin, CosmosMsg, Decimal, Deps, StdError, StdResult, Uint128};
 use cosmwasm_schema::cw_serde;
+use fusion_plus::DexConfig;
+#[cfg(feature = "osmosis")]
 use std::str::FromStr;
+#[cfg(feature = "osmosis")]
+use cosmwasm_std::{Binary, Env};
+#[cfg(feature = "osmosis")]
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool as ConcentratedPool;
+#[cfg(feature = "osmosis")]
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::Pool as StableSwapPool;
+#[cfg(feature = "osmosis")]
+use osmosis_std::types::osmosis::gamm::v1beta1::Pool as BalancerPool;
+#[cfg(feature = "osmosis")]
+use fusion_plus::{
+    parse_osmosis_dec, BALANCER_POOL_TYPE_URL, CONCENTRATED_POOL_TYPE_URL, STABLESWAP_POOL_TYPE_URL,
+};
+#[cfg(feature = "osmosis")]
+use osmosis_std::shim::Timestamp;
+#[cfg(feature = "osmosis")]
 use osmosis_std::types::osmosis::poolmanager::v1beta1::{
-    PoolmanagerQuerier, SwapAmountInRoute,
+    MsgSwapExactAmountIn, PoolmanagerQuerier, SwapAmountInRoute, SwapAmountOutRoute,
 };
-use crate::constants::{DEFAULT_SENDER_ID, TOKEN_DECIMAL_PRECISION};
+#[cfg(feature = "osmosis")]
+use osmosis_std::types::osmosis::twap::v1beta1::TwapQuerier;
+use crate::astroport::AstroportAdapter;
+#[cfg(feature = "osmosis")]
+use crate::constants::DEFAULT_SENDER_ID;
+use crate::error::ContractError;
 
 #[cw_serde]
 pub struct SwapParams {
     pub routes: Vec<SwapRoute>,
     pub min_output_amount: Uint128,
     pub slippage_tolerance: Decimal,
+    /// Makes the swap exact-amount-out: exactly `min_output_amount` is bought, spending at
+    /// most this much input. Unspent input is refunded to the HTLC sender.
+    pub max_input_amount: Option<Uint128>,
+}
+
+impl SwapParams {
+    pub fn is_exact_out(&self) -> bool {
+        self.max_input_amount.is_some()
+    }
 }
 
 #[cw_serde]
 pub struct SwapRoute {
     pub pool_id: u64,
     pub token_out_denom: String,
+    #[serde(default)]
+    pub dex: Dex,
+}
+
+/// DEX a swap hop trades on
+#[cw_serde]
+#[derive(Default)]
+pub enum Dex {
+    /// Osmosis poolmanager pool identified by `pool_id`
+    #[default]
+    Osmosis,
+    /// Astroport pair contract; `pool_id` is unused
+    Astroport { pair: String },
+}
+
+/// Pricing and swap execution for one DEX backend
+pub trait DexAdapter {
+    /// Whether a route hop trades on this DEX
+    fn supports(&self, route: &SwapRoute) -> bool;
+    
+    /// Spot price of a hop, as input per unit of output
+    fn spot_price(&self, deps: Deps, token_in_denom: &str, route: &SwapRoute) -> StdResult<Decimal>;
+    
+    /// Output of swapping exactly `token_in` along the routes
+    fn estimate_swap(&self, deps: Deps, token_in: &Coin, routes: &[SwapRoute]) -> StdResult<Uint128>;
+    
+    /// Swap fee charged by a hop's pool
+    fn swap_fee(&self, deps: Deps, route: &SwapRoute) -> StdResult<Decimal>;
+    
+    /// Message swapping exactly `token_in` along the routes, with output going to `sender`
+    fn swap_msg(
+        &self,
+        sender: &str,
+        token_in: &Coin,
+        routes: &[SwapRoute],
+        min_output_amount: Uint128,
+    ) -> StdResult<CosmosMsg>;
+}
+
+/// Adapter for the DEX the contract is configured to swap on.
+/// Osmosis is only available when built with the `osmosis` feature.
+pub fn adapter(config: &DexConfig) -> Result<Box<dyn DexAdapter>, ContractError> {
+    match config {
+        #[cfg(feature = "osmosis")]
+        DexConfig::Osmosis => Ok(Box::new(OsmosisAdapter)),
+        #[cfg(not(feature = "osmosis"))]
+        DexConfig::Osmosis => Err(ContractError::SwapsUnsupported {}),
+        DexConfig::Astroport { router, factory } => Ok(Box::new(AstroportAdapter {
+            router: router.clone(),
+            factory: factory.clone(),
+        })),
+    }
+}
+
+/// Osmosis poolmanager backend
+#[cfg(feature = "osmosis")]
+pub struct OsmosisAdapter;
+
+#[cfg(feature = "osmosis")]
+impl DexAdapter for OsmosisAdapter {
+    fn supports(&self, route: &SwapRoute) -> bool {
+        route.dex == Dex::Osmosis
+    }
+    
+    fn spot_price(&self, deps: Deps, token_in_denom: &str, route: &SwapRoute) -> StdResult<Decimal> {
+        // Osmosis quotes the base asset in units of the quote asset, so pricing the
+        // output in the input denom gives input per unit of output
+        Ok(query_spot_price(
+            deps,
+            route.pool_id,
+            route.token_out_denom.clone(),
+            token_in_denom.to_string(),
+        )?
+        .spot_price)
+    }
+    
+    fn estimate_swap(&self, deps: Deps, token_in: &Coin, routes: &[SwapRoute]) -> StdResult<Uint128> {
+        let estimate_response = PoolmanagerQuerier::new(&deps.querier)
+            .estimate_swap_exact_amount_in(DEFAULT_SENDER_ID, token_in.to_string(), to_amount_in_routes(routes))
+            .map_err(|e| StdError::generic_err(format!("Failed to estimate swap: {}", e)))?;
+        
+        Uint128::from_str(&estimate_response.token_out_amount)
+    }
+    
+    fn swap_fee(&self, deps: Deps, route: &SwapRoute) -> StdResult<Decimal> {
+        get_pool_swap_fee(deps, route.pool_id)
+    }
+    
+    fn swap_msg(
+        &self,
+        sender: &str,
+        token_in: &Coin,
+        routes: &[SwapRoute],
+        min_output_amount: Uint128,
+    ) -> StdResult<CosmosMsg> {
+        let msg = MsgSwapExactAmountIn {
+            sender: sender.to_string(),
+            routes: to_amount_in_routes(routes),
+            token_in: Some(osmosis_std::types::cosmos::base::v1beta1::Coin {
+                denom: token_in.denom.clone(),
+                amount: token_in.amount.to_string(),
+            }),
+            token_out_min_amount: min_output_amount.to_string(),
+        };
+        
+        Ok(msg.into())
+    }
+}
+
+/// Reject routes with hops on a DEX other than the adapter's
+pub fn validate_routes(adapter: &dyn DexAdapter, routes: &[SwapRoute]) -> StdResult<()> {
+    if let Some(route) = routes.iter().find(|r| !adapter.supports(r)) {
+        return Err(StdError::generic_err(format!(
+            "Swap hop to {} is not on the configured DEX",
+            route.token_out_denom
+        )));
+    }
+    
+    Ok(())
+}
+
+/// Swap quote recorded on an HTLC when its swap is created and when it is executed
+#[cw_serde]
+pub struct SwapQuote {
+    pub token_in_amount: Uint128,
+    pub token_out_amount: Uint128,
+    /// Minimum output the swap was checked against
+    pub min_output_amount: Uint128,
+    pub price_impact: Decimal,
+    pub quoted_at: u64,
 }
 
 #[cw_serde]
@@ -26,14 +187,53 @@ pub struct PriceQueryResponse {
     pub token_out_denom: String,
 }
 
+#[cw_serde]
+pub struct TwapPriceResponse {
+    pub pool_id: u64,
+    pub token_in_denom: String,
+    pub token_out_denom: String,
+    pub spot_price: Decimal,
+    pub twap_price: Decimal,
+    /// Window in seconds the TWAP was taken over, ending at the current block
+    pub window: u64,
+    /// |spot_price - twap_price| / twap_price
+    pub deviation: Decimal,
+}
+
 #[cw_serde]
 pub struct SwapEstimateResponse {
     pub token_out_amount: Uint128,
     pub price_impact: Decimal,
+    /// Total fee of the route, compounded over every hop
     pub swap_fee: Decimal,
+    pub hop_fees: Vec<HopFee>,
+    pub hop_price_impacts: Vec<HopPriceImpact>,
+}
+
+#[cw_serde]
+pub struct HopPriceImpact {
+    pub pool_id: u64,
+    pub spot_price: Decimal,
+    pub token_out_amount: Uint128,
+    pub price_impact: Decimal,
+}
+
+#[cw_serde]
+pub struct SwapExactOutEstimateResponse {
+    pub token_in_amount: Uint128,
+    pub price_impact: Decimal,
+}
+
+#[cw_serde]
+pub struct HopFee {
+    pub pool_id: u64,
+    pub swap_fee: Decimal,
+    /// Denom the fee is charged in, i.e. the hop's input denom
+    pub fee_denom: String,
 }
 
 /// Query spot price from Osmosis pool
+#[cfg(feature = "osmosis")]
 pub fn query_spot_price(
     deps: Deps,
     pool_id: u64,
@@ -46,11 +246,7 @@ pub fn query_spot_price(
         .spot_price(pool_id, base_denom.clone(), quote_denom.clone())
         .map_err(|e| StdError::generic_err(format!("Failed to query spot price: {}", e)))?;
     
-    let spot_price = Decimal::from_atomics(
-        spot_price_response.spot_price.parse::<u128>()
-            .map_err(|e| StdError::generic_err(format!("Failed to parse spot price: {}", e)))?,
-        TOKEN_DECIMAL_PRECISION,
-    ).map_err(|e| StdError::generic_err(format!("Failed to create decimal: {}", e)))?;
+    let spot_price = parse_osmosis_dec(&spot_price_response.spot_price)?;
     
     Ok(PriceQueryResponse {
         spot_price,
@@ -59,91 +255,306 @@ pub fn query_spot_price(
     })
 }
 
-/// Estimate swap output amount with price impact calculation
+/// Query the arithmetic TWAP of an Osmosis pool over `window` seconds up to now,
+/// together with the current spot price
+#[cfg(feature = "osmosis")]
+pub fn query_twap_price(
+    deps: Deps,
+    env: &Env,
+    pool_id: u64,
+    base_denom: String,
+    quote_denom: String,
+    window: u64,
+) -> StdResult<TwapPriceResponse> {
+    let spot_price = query_spot_price(deps, pool_id, base_denom.clone(), quote_denom.clone())?.spot_price;
+    
+    let start_time = Timestamp {
+        seconds: env.block.time.seconds().saturating_sub(window) as i64,
+        nanos: 0,
+    };
+    let twap_response = TwapQuerier::new(&deps.querier)
+        .arithmetic_twap_to_now(pool_id, base_denom.clone(), quote_denom.clone(), Some(start_time))
+        .map_err(|e| StdError::generic_err(format!("Failed to query TWAP: {}", e)))?;
+    
+    let twap_price = parse_osmosis_dec(&twap_response.arithmetic_twap)?;
+    if twap_price.is_zero() {
+        return Err(StdError::generic_err(format!("Pool {} has a zero TWAP", pool_id)));
+    }
+    
+    let deviation = spot_price.abs_diff(twap_price).checked_div(twap_price)
+        .map_err(|e| StdError::generic_err(format!("Failed to compute deviation: {}", e)))?;
+    
+    Ok(TwapPriceResponse {
+        pool_id,
+        token_in_denom: base_denom,
+        token_out_denom: quote_denom,
+        spot_price,
+        twap_price,
+        window,
+        deviation,
+    })
+}
+
+/// Query spot and TWAP prices for every hop of a route
+#[cfg(feature = "osmosis")]
+pub fn query_route_twap_prices(
+    deps: Deps,
+    env: &Env,
+    token_in_denom: &str,
+    routes: &[SwapRoute],
+    window: u64,
+) -> StdResult<Vec<TwapPriceResponse>> {
+    let mut denom_in = token_in_denom.to_string();
+    let mut prices = Vec::with_capacity(routes.len());
+    
+    for route in routes {
+        prices.push(query_twap_price(
+            deps,
+            env,
+            route.pool_id,
+            denom_in,
+            route.token_out_denom.clone(),
+            window,
+        )?);
+        denom_in = route.token_out_denom.clone();
+    }
+    
+    Ok(prices)
+}
+
+/// Estimate an Osmosis swap's output amount with price impact calculation
+#[cfg(feature = "osmosis")]
 pub fn estimate_swap(
     deps: Deps,
     token_in: Coin,
     routes: Vec<SwapRoute>,
 ) -> StdResult<SwapEstimateResponse> {
-    let poolmanager = PoolmanagerQuerier::new(&deps.querier);
-    
-    // Convert our routes to Osmosis routes
-    let osmo_routes: Vec<SwapAmountInRoute> = routes
-        .into_iter()
-        .map(|r| SwapAmountInRoute {
-            pool_id: r.pool_id,
-            token_out_denom: r.token_out_denom,
-        })
-        .collect();
-    
-    let estimate_response = poolmanager
-        .estimate_swap_exact_amount_in(DEFAULT_SENDER_ID, token_in.to_string(), osmo_routes.clone())
-        .map_err(|e| StdError::generic_err(format!("Failed to estimate swap: {}", e)))?;
+    estimate_swap_with(deps, &OsmosisAdapter, token_in, routes)
+}
+
+/// Estimate swap output amount with price impact calculation
+pub fn estimate_swap_with(
+    deps: Deps,
+    adapter: &dyn DexAdapter,
+    token_in: Coin,
+    routes: Vec<SwapRoute>,
+) -> StdResult<SwapEstimateResponse> {
+    validate_routes(adapter, &routes)?;
     
-    let token_out_amount = Uint128::from_str(&estimate_response.token_out_amount)?;
+    let token_out_amount = adapter.estimate_swap(deps, &token_in, &routes)?;
     
-    // Calculate price impact by comparing spot price with effective price
-    let price_impact = calculate_price_impact(
+    // Calculate price impact by comparing spot prices with effective prices
+    let (price_impact, hop_price_impacts) = calculate_price_impact(
         deps,
+        adapter,
         &token_in,
         token_out_amount,
-        &osmo_routes,
+        &routes,
     )?;
     
-    // Get swap fee from the pool
-    let swap_fee = get_pool_swap_fee(deps, osmo_routes[0].pool_id)?;
+    // Get swap fees from every pool along the route
+    let (hop_fees, swap_fee) = get_route_swap_fees(deps, adapter, &token_in.denom, &routes)?;
     
     Ok(SwapEstimateResponse {
         token_out_amount,
         price_impact,
         swap_fee,
+        hop_fees,
+        hop_price_impacts,
+    })
+}
+
+/// Estimate the input needed to receive exactly `token_out_amount` at the end of the route
+#[cfg(feature = "osmosis")]
+pub fn estimate_swap_exact_out(
+    deps: Deps,
+    token_in_denom: String,
+    token_out_amount: Uint128,
+    routes: Vec<SwapRoute>,
+) -> StdResult<SwapExactOutEstimateResponse> {
+    validate_routes(&OsmosisAdapter, &routes)?;
+    let poolmanager = PoolmanagerQuerier::new(&deps.querier);
+    
+    let token_out = Coin {
+        denom: routes
+            .last()
+            .map(|r| r.token_out_denom.clone())
+            .ok_or_else(|| StdError::generic_err("Swap routes cannot be empty"))?,
+        amount: token_out_amount,
+    };
+    
+    let estimate_response = poolmanager
+        .estimate_swap_exact_amount_out(
+            DEFAULT_SENDER_ID,
+            to_amount_out_routes(&token_in_denom, &routes),
+            token_out.to_string(),
+        )
+        .map_err(|e| StdError::generic_err(format!("Failed to estimate swap: {}", e)))?;
+    
+    let token_in_amount = Uint128::from_str(&estimate_response.token_in_amount)?;
+    
+    let (price_impact, _) = calculate_price_impact(
+        deps,
+        &OsmosisAdapter,
+        &Coin {
+            denom: token_in_denom,
+            amount: token_in_amount,
+        },
+        token_out_amount,
+        &routes,
+    )?;
+    
+    Ok(SwapExactOutEstimateResponse {
+        token_in_amount,
+        price_impact,
     })
 }
 
-/// Calculate price impact for a swap
+/// Convert routes to Osmosis exact-in routes
+#[cfg(feature = "osmosis")]
+fn to_amount_in_routes(routes: &[SwapRoute]) -> Vec<SwapAmountInRoute> {
+    routes
+        .iter()
+        .map(|r| SwapAmountInRoute {
+            pool_id: r.pool_id,
+            token_out_denom: r.token_out_denom.clone(),
+        })
+        .collect()
+}
+
+/// Convert routes to Osmosis exact-out routes, which name each hop's input denom instead of its output
+#[cfg(feature = "osmosis")]
+pub fn to_amount_out_routes(token_in_denom: &str, routes: &[SwapRoute]) -> Vec<SwapAmountOutRoute> {
+    let mut token_in_denom = token_in_denom.to_string();
+    routes
+        .iter()
+        .map(|r| {
+            let route = SwapAmountOutRoute {
+                pool_id: r.pool_id,
+                token_in_denom: token_in_denom.clone(),
+            };
+            token_in_denom = r.token_out_denom.clone();
+            route
+        })
+        .collect()
+}
+
+/// Calculate price impact for a swap, in total and per hop.
+/// Spot prices are chained along the route and compared with the estimated output.
 fn calculate_price_impact(
     deps: Deps,
+    adapter: &dyn DexAdapter,
     token_in: &Coin,
     token_out_amount: Uint128,
-    routes: &[SwapAmountInRoute],
-) -> StdResult<Decimal> {
-    // For single route swaps
-    if routes.len() == 1 {
-        let route = &routes[0];
-        
+    routes: &[SwapRoute],
+) -> StdResult<(Decimal, Vec<HopPriceImpact>)> {
+    let mut hop_price_impacts = Vec::with_capacity(routes.len());
+    let mut route_spot_price = Decimal::one();
+    let mut hop_in = token_in.clone();
+    
+    for route in routes {
         // Get spot price before swap
-        let spot_price = query_spot_price(
-            deps,
-            route.pool_id,
-            token_in.denom.clone(),
-            route.token_out_denom.clone(),
-        )?;
+        let spot_price = adapter.spot_price(deps, &hop_in.denom, route)?;
         
-        // Calculate effective price
-        let effective_price = Decimal::from_ratio(token_in.amount, token_out_amount);
+        // Single hop routes already have their output; otherwise estimate this hop alone
+        let hop_out_amount = if routes.len() == 1 {
+            token_out_amount
+        } else {
+            adapter.estimate_swap(deps, &hop_in, std::slice::from_ref(route))?
+        };
         
-        // Price impact = (effective_price - spot_price) / spot_price
-        let price_impact = effective_price
-            .checked_sub(spot_price.spot_price)
-            .unwrap_or_default()
-            .checked_div(spot_price.spot_price)
-            .unwrap_or_default();
+        hop_price_impacts.push(HopPriceImpact {
+            pool_id: route.pool_id,
+            spot_price,
+            token_out_amount: hop_out_amount,
+            price_impact: relative_price_impact(hop_in.amount, hop_out_amount, spot_price),
+        });
         
-        Ok(price_impact)
-    } else {
-        // For multi-hop swaps, aggregate price impact
-        // This is a simplified calculation
-        Ok(Decimal::percent(1)) // Default 1% for multi-hop
+        route_spot_price = route_spot_price.checked_mul(spot_price)?;
+        hop_in = Coin {
+            denom: route.token_out_denom.clone(),
+            amount: hop_out_amount,
+        };
     }
+    
+    let price_impact = relative_price_impact(token_in.amount, token_out_amount, route_spot_price);
+    
+    Ok((price_impact, hop_price_impacts))
 }
 
-/// Get swap fee for a pool
-fn get_pool_swap_fee(deps: Deps, pool_id: u64) -> StdResult<Decimal> {
-    // Query pool details to get swap fee
-    // For now, return default Osmosis swap fee
-    let _ = deps;
-    let _ = pool_id;
-    Ok(Decimal::permille(3)) // 0.3% default
+/// Price impact = (effective_price - spot_price) / spot_price,
+/// with both prices as input per unit of output
+fn relative_price_impact(amount_in: Uint128, amount_out: Uint128, spot_price: Decimal) -> Decimal {
+    if amount_out.is_zero() {
+        return Decimal::one();
+    }
+    
+    // Calculate effective price
+    let effective_price = Decimal::from_ratio(amount_in, amount_out);
+    
+    effective_price
+        .checked_sub(spot_price)
+        .unwrap_or_default()
+        .checked_div(spot_price)
+        .unwrap_or_default()
+}
+
+/// Get per-hop swap fees for a route and the total fee compounded over all hops
+pub fn get_route_swap_fees(
+    deps: Deps,
+    adapter: &dyn DexAdapter,
+    token_in_denom: &str,
+    routes: &[SwapRoute],
+) -> StdResult<(Vec<HopFee>, Decimal)> {
+    let mut hop_fees = Vec::with_capacity(routes.len());
+    let mut retained = Decimal::one();
+    let mut fee_denom = token_in_denom.to_string();
+    
+    for route in routes {
+        let swap_fee = adapter.swap_fee(deps, route)?;
+        retained *= Decimal::one() - swap_fee;
+        
+        hop_fees.push(HopFee {
+            pool_id: route.pool_id,
+            swap_fee,
+            fee_denom,
+        });
+        fee_denom = route.token_out_denom.clone();
+    }
+    
+    Ok((hop_fees, Decimal::one() - retained))
+}
+
+/// Get swap fee (spread factor) for a pool
+#[cfg(feature = "osmosis")]
+pub fn get_pool_swap_fee(deps: Deps, pool_id: u64) -> StdResult<Decimal> {
+    let poolmanager = PoolmanagerQuerier::new(&deps.querier);
+    
+    let pool = poolmanager
+        .pool(pool_id)
+        .map_err(|e| StdError::generic_err(format!("Failed to query pool {}: {}", pool_id, e)))?
+        .pool
+        .ok_or_else(|| StdError::generic_err(format!("Pool {} not found", pool_id)))?;
+    
+    let value = Binary::from(pool.value);
+    let swap_fee = match pool.type_url.as_str() {
+        BALANCER_POOL_TYPE_URL => BalancerPool::try_from(value)?
+            .pool_params
+            .map(|p| p.swap_fee),
+        STABLESWAP_POOL_TYPE_URL => StableSwapPool::try_from(value)?
+            .pool_params
+            .map(|p| p.swap_fee),
+        CONCENTRATED_POOL_TYPE_URL => Some(ConcentratedPool::try_from(value)?.spread_factor),
+        other => {
+            return Err(StdError::generic_err(format!(
+                "Unsupported pool type for pool {}: {}",
+                pool_id, other
+            )))
+        }
+    }
+    .ok_or_else(|| StdError::generic_err(format!("Pool {} has no pool params", pool_id)))?;
+    
+    parse_osmosis_dec(&swap_fee)
 }
 
 /// Validate swap parameters
@@ -160,6 +571,10 @@ pub fn validate_swap_params(swap_params: &SwapParams) -> StdResult<()> {
         return Err(StdError::generic_err("Slippage tolerance cannot exceed 50%"));
     }
     
+    if swap_params.max_input_amount == Some(Uint128::zero()) {
+        return Err(StdError::generic_err("Maximum input amount must be greater than zero"));
+    }
+    
     Ok(())
 }
 
@@ -187,9 +602,11 @@ mod tests {
             routes: vec![SwapRoute {
                 pool_id: 1,
                 token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
             }],
             min_output_amount: Uint128::new(1000),
             slippage_tolerance: Decimal::percent(1),
+            max_input_amount: None,
         };
         assert!(validate_swap_params(&valid_params).is_ok());
         
@@ -198,6 +615,7 @@ mod tests {
             routes: vec![],
             min_output_amount: Uint128::new(1000),
             slippage_tolerance: Decimal::percent(1),
+            max_input_amount: None,
         };
         assert!(validate_swap_params(&invalid_params).is_err());
         
@@ -206,9 +624,11 @@ mod tests {
             routes: vec![SwapRoute {
                 pool_id: 1,
                 token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
             }],
             min_output_amount: Uint128::zero(),
             slippage_tolerance: Decimal::percent(1),
+            max_input_amount: None,
         };
         assert!(validate_swap_params(&invalid_params).is_err());
         
@@ -217,13 +637,64 @@ mod tests {
             routes: vec![SwapRoute {
                 pool_id: 1,
                 token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
             }],
             min_output_amount: Uint128::new(1000),
             slippage_tolerance: Decimal::percent(51),
+            max_input_amount: None,
+        };
+        assert!(validate_swap_params(&invalid_params).is_err());
+        
+        // Zero max input on an exact-out swap
+        let invalid_params = SwapParams {
+            routes: vec![SwapRoute {
+                pool_id: 1,
+                token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
+            }],
+            min_output_amount: Uint128::new(1000),
+            slippage_tolerance: Decimal::percent(1),
+            max_input_amount: Some(Uint128::zero()),
         };
         assert!(validate_swap_params(&invalid_params).is_err());
     }
     
+    #[test]
+    #[cfg(feature = "osmosis")]
+    fn test_to_amount_out_routes() {
+        let routes = vec![
+            SwapRoute {
+                pool_id: 1,
+                token_out_denom: "uusdc".to_string(),
+                dex: Dex::Osmosis,
+            },
+            SwapRoute {
+                pool_id: 2,
+                token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
+            },
+        ];
+        
+        let out_routes = to_amount_out_routes("uatom", &routes);
+        assert_eq!(out_routes[0].pool_id, 1);
+        assert_eq!(out_routes[0].token_in_denom, "uatom");
+        assert_eq!(out_routes[1].pool_id, 2);
+        assert_eq!(out_routes[1].token_in_denom, "uusdc");
+    }
+    
+    #[test]
+    fn test_relative_price_impact() {
+        // 100 in for 95 out at a spot price of 1.0 is a ~5.26% impact
+        let impact = relative_price_impact(Uint128::new(100), Uint128::new(95), Decimal::one());
+        assert_eq!(impact, Decimal::from_ratio(100u128, 95u128) - Decimal::one());
+        
+        // Better than spot saturates at zero
+        assert_eq!(relative_price_impact(Uint128::new(100), Uint128::new(110), Decimal::one()), Decimal::zero());
+        
+        // No output is a total loss
+        assert_eq!(relative_price_impact(Uint128::new(100), Uint128::zero(), Decimal::one()), Decimal::one());
+    }
+    
     #[test]
     fn test_calculate_min_output_with_slippage() {
         let estimated = Uint128::new(10000);
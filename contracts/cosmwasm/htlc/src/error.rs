@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -18,6 +18,12 @@ pub enum ContractError {
     #[error("Invalid secret")]
     InvalidSecret {},
 
+    #[error("Invalid secret length: expected {expected} bytes, got {actual}")]
+    InvalidSecretLength { expected: u32, actual: u32 },
+
+    #[error("Invalid preimage length: {length} (must be between 1 and {max} bytes)")]
+    InvalidPreimageLength { length: u32, max: u32 },
+
     #[error("Timelock not expired")]
     TimelockNotExpired {},
 
@@ -50,4 +56,28 @@ pub enum ContractError {
     
     #[error("Swap already executed")]
     SwapAlreadyExecuted {},
+
+    #[error("Required input amount exceeds maximum input amount")]
+    ExcessiveInputAmount {},
+
+    #[error("Effective minimum output {effective_min} is below the minimum output amount {floor}")]
+    SlippageFloorBreached { effective_min: Uint128, floor: Uint128 },
+
+    #[error("Minimum output {min_output} is below the floor {floor} set at creation")]
+    MinOutputBelowFloor { min_output: Uint128, floor: Uint128 },
+
+    #[error("Price impact {impact} exceeds maximum {max}")]
+    PriceImpactTooHigh { impact: Decimal, max: Decimal },
+
+    #[error("Spot price of pool {pool_id} deviates {deviation} from its TWAP (max {max})")]
+    TwapDeviationTooHigh { pool_id: u64, deviation: Decimal, max: Decimal },
+
+    #[error("Swaps unsupported: contract was built without the osmosis feature")]
+    SwapsUnsupported {},
+
+    #[error("IBC hooks unsupported: contract was built without the osmosis feature")]
+    IbcHooksUnsupported {},
+
+    #[error("HTLC has no transferable claim token")]
+    ClaimNotTransferable {},
 }
\ No newline at end of file
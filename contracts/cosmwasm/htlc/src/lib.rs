@@ -1,15 +1,19 @@
+pub mod astroport;
+pub mod claim_token;
 pub mod constants;
 pub mod contract;
 pub mod dex;
 pub mod error;
+#[cfg(feature = "osmosis")]
+pub mod ibc_hooks;
 pub mod msg;
 pub mod state;
 
 // Test-only modules - not included in production builds
-#[cfg(test)]
+#[cfg(all(test, feature = "osmosis"))]
 pub mod test_helpers;
 
-#[cfg(test)]
+#[cfg(all(test, feature = "osmosis"))]
 pub mod test_osmosis;
 
 pub use crate::error::ContractError;
\ No newline at end of file
@@ -1,6 +1,14 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Coin;
-use crate::dex::{SwapParams, PriceQueryResponse, SwapEstimateResponse};
+use cosmwasm_std::{Binary, Coin, Uint128};
+use cw721::{
+    ContractInfoResponse, NftInfoResponse, NumTokensResponse, OwnerOfResponse, TokensResponse,
+};
+use crate::dex::{
+    PriceQueryResponse, SwapEstimateResponse, SwapExactOutEstimateResponse, SwapParams,
+    SwapQuote, TwapPriceResponse,
+};
+use crate::state::IbcOrigin;
+use fusion_plus::{ConfigResponse, ProtocolConfig};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -15,6 +23,8 @@ pub enum ExecuteMsg {
         timelock: u64,    // unix timestamp
         target_chain: String,
         target_address: String,
+        preimage_length: Option<u32>, // secret length in bytes, defaults to 32
+        transferable: Option<bool>,   // mint a cw721 token representing the receiver's claim
     },
     Withdraw {
         htlc_id: String,
@@ -30,11 +40,59 @@ pub enum ExecuteMsg {
         target_chain: String,
         target_address: String,
         swap_params: SwapParams,
+        preimage_length: Option<u32>,
+        transferable: Option<bool>,
     },
     ExecuteSwapAndLock {
         htlc_id: String,
         swap_params: SwapParams,
     },
+    /// Update protocol configuration (admin only)
+    UpdateConfig { config: ProtocolConfig },
+    /// Sent through an ibc-hooks `wasm` memo so the incoming transfer funds the HTLC.
+    /// `channel` is the local channel the transfer arrived on (the packet's destination
+    /// channel), which together with `original_sender` must derive to the executing address.
+    CreateHtlcFromIbc {
+        channel: String,
+        original_sender: String,
+        receiver: String,
+        hashlock: String,
+        timelock: u64,
+        target_chain: String,
+        target_address: String,
+        preimage_length: Option<u32>,
+    },
+    /// cw721: transfer a claim token, making the recipient the HTLC receiver
+    TransferNft {
+        recipient: String,
+        token_id: String,
+    },
+    /// cw721: transfer a claim token to a contract and trigger its ReceiveNft handler
+    SendNft {
+        contract: String,
+        token_id: String,
+        msg: Binary,
+    },
+}
+
+/// Callbacks delivered by the Osmosis ibc-hooks module
+#[cw_serde]
+pub enum SudoMsg {
+    #[serde(rename = "ibc_lifecycle_complete")]
+    IBCLifecycleComplete(IBCLifecycleComplete),
+}
+
+#[cw_serde]
+pub enum IBCLifecycleComplete {
+    #[serde(rename = "ibc_ack")]
+    IBCAck {
+        channel: String,
+        sequence: u64,
+        ack: String,
+        success: bool,
+    },
+    #[serde(rename = "ibc_timeout")]
+    IBCTimeout { channel: String, sequence: u64 },
 }
 
 #[cw_serde]
@@ -43,12 +101,31 @@ pub enum QueryMsg {
     #[returns(HtlcResponse)]
     GetHtlc { htlc_id: String },
     
+    #[returns(ConfigResponse)]
+    GetConfig {},
+    
     #[returns(ListHtlcsResponse)]
     ListHtlcs {
         start_after: Option<String>,
         limit: Option<u32>,
     },
     
+    /// Open HTLCs whose timelock expires after the current block time and at or before `before`
+    #[returns(ListHtlcExpiryResponse)]
+    ListExpiring {
+        before: u64,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    
+    /// Expired HTLCs that have been neither withdrawn nor refunded
+    #[returns(ListHtlcExpiryResponse)]
+    ListRefundable {
+        sender: Option<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    
     #[returns(PriceQueryResponse)]
     QuerySpotPrice {
         pool_id: u64,
@@ -56,11 +133,58 @@ pub enum QueryMsg {
         quote_denom: String,
     },
     
+    /// Spot price and arithmetic TWAP over `window` seconds (defaults to the configured window)
+    #[returns(TwapPriceResponse)]
+    QueryTwapPrice {
+        pool_id: u64,
+        base_denom: String,
+        quote_denom: String,
+        window: Option<u64>,
+    },
+    
+    #[returns(SecretResponse)]
+    GetSecret { hashlock: String },
+    
     #[returns(SwapEstimateResponse)]
     EstimateSwap {
         token_in: Coin,
         routes: Vec<crate::dex::SwapRoute>,
     },
+    
+    /// Input needed to receive exactly `token_out_amount` at the end of the route
+    #[returns(SwapExactOutEstimateResponse)]
+    EstimateSwapExactOut {
+        token_in_denom: String,
+        token_out_amount: Uint128,
+        routes: Vec<crate::dex::SwapRoute>,
+    },
+    
+    /// cw721: current holder of a claim token
+    #[returns(OwnerOfResponse)]
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    
+    /// cw721: claim token metadata, carrying the underlying HTLC as extension
+    #[returns(NftInfoResponse<HtlcResponse>)]
+    NftInfo { token_id: String },
+    
+    /// cw721: number of live claim tokens
+    #[returns(NumTokensResponse)]
+    NumTokens {},
+    
+    /// cw721: claim tokens held by an owner
+    #[returns(TokensResponse)]
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    
+    /// cw721: collection name and symbol
+    #[returns(ContractInfoResponse)]
+    ContractInfo {},
 }
 
 #[cw_serde]
@@ -70,6 +194,7 @@ pub struct HtlcResponse {
     pub receiver: String,
     pub amount: Vec<Coin>,
     pub hashlock: String,
+    pub preimage_length: u32,
     pub timelock: u64,
     pub withdrawn: bool,
     pub refunded: bool,
@@ -77,9 +202,32 @@ pub struct HtlcResponse {
     pub target_address: String,
     pub swap_params: Option<SwapParams>,
     pub swap_executed: bool,
+    pub creation_quote: Option<SwapQuote>,
+    pub execution_quote: Option<SwapQuote>,
+    pub secret: Option<String>,
+    pub claim_token: bool,
+    pub ibc_origin: Option<IbcOrigin>,
 }
 
 #[cw_serde]
 pub struct ListHtlcsResponse {
     pub htlcs: Vec<HtlcResponse>,
+}
+
+#[cw_serde]
+pub struct HtlcExpiryResponse {
+    pub htlc: HtlcResponse,
+    /// Seconds until the timelock expires relative to the block time, negative once expired
+    pub seconds_remaining: i64,
+}
+
+#[cw_serde]
+pub struct ListHtlcExpiryResponse {
+    pub htlcs: Vec<HtlcExpiryResponse>,
+}
+
+#[cw_serde]
+pub struct SecretResponse {
+    pub hashlock: String,
+    pub secret: Option<String>,
 }
\ No newline at end of file
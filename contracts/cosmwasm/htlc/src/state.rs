@@ -1,7 +1,7 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin};
+use cosmwasm_std::{Addr, Coin, Empty, Uint128};
 use cw_storage_plus::{Item, Map};
-use crate::dex::SwapParams;
+use crate::dex::{SwapParams, SwapQuote};
 use fusion_plus::ProtocolConfig;
 
 #[cw_serde]
@@ -16,6 +16,8 @@ pub struct Htlc {
     pub receiver: Addr,
     pub amount: Vec<Coin>,
     pub hashlock: String,
+    /// Exact length in bytes the revealed preimage must have
+    pub preimage_length: u32,
     pub timelock: u64,
     pub withdrawn: bool,
     pub refunded: bool,
@@ -23,8 +25,35 @@ pub struct Htlc {
     pub target_address: String,
     pub swap_params: Option<SwapParams>,
     pub swap_executed: bool,
+    /// Quote taken when the HTLC was created with a swap
+    pub creation_quote: Option<SwapQuote>,
+    /// Quote taken when the swap was executed
+    pub execution_quote: Option<SwapQuote>,
+    /// Hex encoded preimage, recorded once the HTLC has been withdrawn
+    pub secret: Option<String>,
+    /// Whether the receiver's claim is represented by a cw721 token (token id = HTLC id)
+    pub claim_token: bool,
+    /// Set when the HTLC was funded by an ibc-hooks transfer; refunds go back over IBC
+    pub ibc_origin: Option<IbcOrigin>,
+}
+
+#[cw_serde]
+pub struct IbcOrigin {
+    /// Local end of the channel the funding transfer arrived on; refunds are sent out over it
+    pub channel: String,
+    pub original_sender: String,
 }
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const HTLCS: Map<&str, Htlc> = Map::new("htlcs");
-pub const HTLC_COUNT: Item<u64> = Item::new("htlc_count");
\ No newline at end of file
+pub const HTLC_COUNT: Item<u64> = Item::new("htlc_count");
+pub const SECRETS: Map<&str, String> = Map::new("secrets"); // hashlock -> revealed secret
+pub const TIMELOCK_INDEX: Map<(u64, &str), Empty> = Map::new("timelock_index"); // (timelock, htlc_id) of open HTLCs
+pub const CLAIM_TOKENS: Map<(&Addr, &str), Empty> = Map::new("claim_tokens"); // (owner, token_id) of live claim tokens
+pub const CLAIM_TOKEN_COUNT: Item<u64> = Item::new("claim_token_count");
+#[cfg(feature = "osmosis")]
+pub const PENDING_IBC_REFUND: Item<String> = Item::new("pending_ibc_refund"); // htlc_id awaiting its transfer reply
+#[cfg(feature = "osmosis")]
+pub const IBC_REFUNDS: Map<(&str, u64), String> = Map::new("ibc_refunds"); // (channel, sequence) -> htlc_id
+pub const PENDING_SWAP: Item<String> = Item::new("pending_swap"); // htlc_id awaiting its swap reply
+pub const PENDING_SWAP_BALANCE: Item<Uint128> = Item::new("pending_swap_balance"); // output denom balance before an exact-in swap
\ No newline at end of file
@@ -1,128 +1,317 @@
-#[cfg(test)]
-pub mod test_helpers {
-    use cosmwasm_std::{
-        from_json,
-        testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR},
-        to_json_binary, ContractResult, OwnedDeps, Querier, 
-        QuerierResult, QueryRequest, SystemError, SystemResult,
-    };
-    use osmosis_std::types::osmosis::poolmanager::v1beta1::{
-        EstimateSwapExactAmountInResponse, SpotPriceResponse,
-    };
-    use std::collections::HashMap;
+use cosmwasm_std::{
+    from_json,
+    testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR},
+    to_json_binary, Coin, ContractResult, Decimal, OwnedDeps, Querier, 
+    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128,
+};
+use osmosis_std::shim::Any;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool as ConcentratedPool;
+use osmosis_std::types::osmosis::gamm::v1beta1::{Pool as BalancerPool, PoolParams};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    EstimateSwapExactAmountInRequest, EstimateSwapExactAmountInResponse,
+    EstimateSwapExactAmountOutRequest, EstimateSwapExactAmountOutResponse, PoolRequest,
+    PoolResponse, SpotPriceRequest, SpotPriceResponse,
+};
+use osmosis_std::types::osmosis::twap::v1beta1::{
+    ArithmeticTwapToNowRequest, ArithmeticTwapToNowResponse,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+use fusion_plus::{parse_osmosis_dec, BALANCER_POOL_TYPE_URL, CONCENTRATED_POOL_TYPE_URL};
 
-    /// Custom querier that handles Osmosis-specific queries
-    pub struct OsmosisQuerier {
-        base: MockQuerier,
-        pool_configs: HashMap<u64, PoolConfig>,
+/// Custom querier that handles Osmosis-specific queries
+pub struct OsmosisQuerier {
+    base: MockQuerier,
+    pool_configs: HashMap<u64, PoolConfig>,
+}
+
+#[derive(Clone)]
+struct PoolConfig {
+    spot_prices: HashMap<(String, String), String>,
+    twap_prices: HashMap<(String, String), String>, // defaults to the spot price
+    swap_rates: HashMap<String, String>, // token_in_denom -> output ratio
+    swap_fee: String,
+    concentrated: bool,
+}
+
+impl OsmosisQuerier {
+    pub fn new(base: MockQuerier) -> Self {
+        Self {
+            base,
+            pool_configs: HashMap::new(),
+        }
     }
 
-    #[derive(Clone)]
-    struct PoolConfig {
-        spot_prices: HashMap<(String, String), String>,
-        swap_rates: HashMap<String, String>, // token_in_denom -> output ratio
+    /// Configure a pool with spot price and swap rate
+    pub fn with_pool(
+        mut self,
+        pool_id: u64,
+        base_denom: &str,
+        quote_denom: &str,
+        spot_price: &str,
+        swap_rate: f64, // e.g., 0.95 means 95% output
+    ) -> Self {
+        let pool_config = self.pool_configs.entry(pool_id).or_insert(PoolConfig {
+            spot_prices: HashMap::new(),
+            twap_prices: HashMap::new(),
+            swap_rates: HashMap::new(),
+            swap_fee: "0.003000000000000000".to_string(),
+            concentrated: false,
+        });
+        
+        // Set spot price for both directions; `spot_price` is quote per unit of base
+        let inverse = Decimal::one() / parse_osmosis_dec(spot_price).unwrap();
+        pool_config.spot_prices.insert(
+            (base_denom.to_string(), quote_denom.to_string()),
+            spot_price.to_string(),
+        );
+        pool_config.spot_prices.insert(
+            (quote_denom.to_string(), base_denom.to_string()),
+            inverse.atomics().to_string(),
+        );
+        
+        // Set swap rate
+        pool_config.swap_rates.insert(
+            base_denom.to_string(),
+            swap_rate.to_string(),
+        );
+        
+        self
     }
 
-    impl OsmosisQuerier {
-        pub fn new(base: MockQuerier) -> Self {
-            Self {
-                base,
-                pool_configs: HashMap::new(),
-            }
-        }
+    /// Set the arithmetic TWAP of a configured pair, which otherwise equals its spot price
+    pub fn with_twap(mut self, pool_id: u64, base_denom: &str, quote_denom: &str, twap: &str) -> Self {
+        let pool_config = self.pool_configs.get_mut(&pool_id).expect("pool not configured");
+        pool_config.twap_prices.insert(
+            (base_denom.to_string(), quote_denom.to_string()),
+            twap.to_string(),
+        );
+        self
+    }
 
-        /// Configure a pool with spot price and swap rate
-        pub fn with_pool(
-            mut self,
-            pool_id: u64,
-            base_denom: &str,
-            quote_denom: &str,
-            spot_price: &str,
-            swap_rate: f64, // e.g., 0.95 means 95% output
-        ) -> Self {
-            let pool_config = self.pool_configs.entry(pool_id).or_insert(PoolConfig {
-                spot_prices: HashMap::new(),
-                swap_rates: HashMap::new(),
-            });
-            
-            // Set spot price for both directions
-            pool_config.spot_prices.insert(
-                (base_denom.to_string(), quote_denom.to_string()),
-                spot_price.to_string(),
-            );
-            
-            // Set swap rate
-            pool_config.swap_rates.insert(
-                base_denom.to_string(),
-                swap_rate.to_string(),
-            );
-            
-            self
+    /// Override the swap fee of a configured balancer pool
+    pub fn with_swap_fee(mut self, pool_id: u64, swap_fee: &str) -> Self {
+        let pool_config = self.pool_configs.get_mut(&pool_id).expect("pool not configured");
+        pool_config.swap_fee = swap_fee.to_string();
+        pool_config.concentrated = false;
+        self
+    }
+
+    /// Turn a configured pool into a concentrated liquidity pool with the given spread factor
+    pub fn with_spread_factor(mut self, pool_id: u64, spread_factor: &str) -> Self {
+        let pool_config = self.pool_configs.get_mut(&pool_id).expect("pool not configured");
+        pool_config.swap_fee = spread_factor.to_string();
+        pool_config.concentrated = true;
+        self
+    }
+
+    /// Set the bank balance of an address
+    pub fn update_balance(&mut self, addr: impl Into<String>, balance: Vec<Coin>) {
+        self.base.update_balance(addr, balance);
+    }
+
+    fn pool_any(pool_id: u64, pool_config: &PoolConfig) -> Any {
+        if pool_config.concentrated {
+            let pool = ConcentratedPool {
+                id: pool_id,
+                spread_factor: pool_config.swap_fee.clone(),
+                ..Default::default()
+            };
+            Any {
+                type_url: CONCENTRATED_POOL_TYPE_URL.to_string(),
+                value: cosmwasm_std::Binary::from(pool).to_vec(),
+            }
+        } else {
+            let pool = BalancerPool {
+                id: pool_id,
+                pool_params: Some(PoolParams {
+                    swap_fee: pool_config.swap_fee.clone(),
+                    exit_fee: "0".to_string(),
+                    smooth_weight_change_params: None,
+                }),
+                ..Default::default()
+            };
+            Any {
+                type_url: BALANCER_POOL_TYPE_URL.to_string(),
+                value: cosmwasm_std::Binary::from(pool).to_vec(),
+            }
         }
     }
+}
 
-    impl Querier for OsmosisQuerier {
-        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
-            // First check if this is an Osmosis-specific query
-            let request: Result<QueryRequest<cosmwasm_std::Empty>, _> = from_json(bin_request);
-            
-            if let Ok(QueryRequest::Stargate { path, data }) = request {
-                // Handle Osmosis poolmanager queries
-                if path == "/osmosis.poolmanager.v1beta1.Query/SpotPrice" {
-                    // Decode the spot price request
-                    // For simplicity in tests, we'll parse a custom format
-                    let _data_str = String::from_utf8_lossy(&data);
-                    
-                    // Look for pool 1 with uatom/uosmo (default test case)
-                    if let Some(pool_config) = self.pool_configs.get(&1) {
-                        if let Some(spot_price) = pool_config.spot_prices.get(&("uatom".to_string(), "uosmo".to_string())) {
-                            let response = SpotPriceResponse {
-                                spot_price: spot_price.clone(),
-                            };
-                            
-                            let response_binary = to_json_binary(&response).unwrap();
-                            return SystemResult::Ok(ContractResult::Ok(response_binary));
+impl Querier for OsmosisQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        // First check if this is an Osmosis-specific query
+        let request: Result<QueryRequest<cosmwasm_std::Empty>, _> = from_json(bin_request);
+        
+        if let Ok(QueryRequest::Stargate { path, data }) = request {
+            // Handle Osmosis poolmanager queries
+            if path == "/osmosis.poolmanager.v1beta1.Query/SpotPrice" {
+                // Decode the spot price request and look up the configured pair
+                if let Ok(req) = SpotPriceRequest::try_from(data.clone()) {
+                    let spot_price = self.pool_configs.get(&req.pool_id).and_then(|pool_config| {
+                        pool_config
+                            .spot_prices
+                            .get(&(req.base_asset_denom.clone(), req.quote_asset_denom.clone()))
+                    });
+                    if let Some(spot_price) = spot_price {
+                        let response = SpotPriceResponse {
+                            spot_price: spot_price.clone(),
+                        };
+                        
+                        let response_binary = to_json_binary(&response).unwrap();
+                        return SystemResult::Ok(ContractResult::Ok(response_binary));
+                    }
+                }
+                
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: "Pool not found in mock".to_string(),
+                    request: data.clone(),
+                });
+            }
+            else if path == "/osmosis.poolmanager.v1beta1.Query/EstimateSwapExactAmountIn" {
+                // For swap estimates, apply each hop's configured swap rate to the input
+                let req = match EstimateSwapExactAmountInRequest::try_from(data.clone()) {
+                    Ok(req) => req,
+                    Err(e) => {
+                        return SystemResult::Err(SystemError::InvalidRequest {
+                            error: e.to_string(),
+                            request: data.clone(),
+                        })
+                    }
+                };
+                
+                let split = req.token_in.find(|c: char| !c.is_ascii_digit()).unwrap_or(req.token_in.len());
+                let mut amount = Uint128::from_str(&req.token_in[..split]).unwrap_or_default();
+                let mut denom = req.token_in[split..].to_string();
+                
+                for route in &req.routes {
+                    let rate = self
+                        .pool_configs
+                        .get(&route.pool_id)
+                        .and_then(|pool_config| pool_config.swap_rates.get(&denom));
+                    match rate {
+                        Some(rate) => amount = amount * Decimal::from_str(rate).unwrap(),
+                        None => {
+                            return SystemResult::Err(SystemError::InvalidRequest {
+                                error: "Pool not found in mock".to_string(),
+                                request: data.clone(),
+                            })
                         }
                     }
-                    
-                    return SystemResult::Err(SystemError::InvalidRequest {
-                        error: "Pool not found in mock".to_string(),
-                        request: data.clone(),
+                    denom = route.token_out_denom.clone();
+                }
+                
+                let response = EstimateSwapExactAmountInResponse {
+                    token_out_amount: amount.to_string(),
+                };
+                
+                let response_binary = to_json_binary(&response).unwrap();
+                return SystemResult::Ok(ContractResult::Ok(response_binary));
+            }
+            else if path == "/osmosis.poolmanager.v1beta1.Query/EstimateSwapExactAmountOut" {
+                // Walk the route backwards, dividing by each hop's swap rate
+                let req = match EstimateSwapExactAmountOutRequest::try_from(data.clone()) {
+                    Ok(req) => req,
+                    Err(e) => {
+                        return SystemResult::Err(SystemError::InvalidRequest {
+                            error: e.to_string(),
+                            request: data.clone(),
+                        })
+                    }
+                };
+                
+                let split = req.token_out.find(|c: char| !c.is_ascii_digit()).unwrap_or(req.token_out.len());
+                let mut amount = Uint128::from_str(&req.token_out[..split]).unwrap_or_default();
+                
+                for route in req.routes.iter().rev() {
+                    let rate = self
+                        .pool_configs
+                        .get(&route.pool_id)
+                        .and_then(|pool_config| pool_config.swap_rates.get(&route.token_in_denom));
+                    match rate {
+                        Some(rate) => amount = amount.div_ceil(Decimal::from_str(rate).unwrap()),
+                        None => {
+                            return SystemResult::Err(SystemError::InvalidRequest {
+                                error: "Pool not found in mock".to_string(),
+                                request: data.clone(),
+                            })
+                        }
+                    }
+                }
+                
+                let response = EstimateSwapExactAmountOutResponse {
+                    token_in_amount: amount.to_string(),
+                };
+                
+                let response_binary = to_json_binary(&response).unwrap();
+                return SystemResult::Ok(ContractResult::Ok(response_binary));
+            }
+            else if path == "/osmosis.twap.v1beta1.Query/ArithmeticTwapToNow" {
+                // Return the configured TWAP, falling back to the spot price
+                if let Ok(req) = ArithmeticTwapToNowRequest::try_from(data.clone()) {
+                    let pair = (req.base_asset.clone(), req.quote_asset.clone());
+                    let twap = self.pool_configs.get(&req.pool_id).and_then(|pool_config| {
+                        pool_config
+                            .twap_prices
+                            .get(&pair)
+                            .or_else(|| pool_config.spot_prices.get(&pair))
                     });
+                    if let Some(twap) = twap {
+                        let response = ArithmeticTwapToNowResponse {
+                            arithmetic_twap: twap.clone(),
+                        };
+                        
+                        let response_binary = to_json_binary(&response).unwrap();
+                        return SystemResult::Ok(ContractResult::Ok(response_binary));
+                    }
                 }
-                else if path == "/osmosis.poolmanager.v1beta1.Query/EstimateSwapExactAmountIn" {
-                    // For swap estimates, return a simple calculation based on swap rate
-                    // Default to 95% output for tests
-                    let response = EstimateSwapExactAmountInResponse {
-                        token_out_amount: "95".to_string(), // 95 uosmo for 100 uatom
+                
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: "Pool not found in mock".to_string(),
+                    request: data.clone(),
+                });
+            }
+            else if path == "/osmosis.poolmanager.v1beta1.Query/Pool" {
+                // Return the configured pool with its fee parameters
+                let pool_id = PoolRequest::try_from(data.clone()).map(|r| r.pool_id).unwrap_or_default();
+                
+                if let Some(pool_config) = self.pool_configs.get(&pool_id) {
+                    let response = PoolResponse {
+                        pool: Some(Self::pool_any(pool_id, pool_config)),
                     };
                     
                     let response_binary = to_json_binary(&response).unwrap();
                     return SystemResult::Ok(ContractResult::Ok(response_binary));
                 }
                 
-                // Return error for unhandled Osmosis queries
                 return SystemResult::Err(SystemError::InvalidRequest {
-                    error: format!("Unhandled Osmosis query path: {}", path),
+                    error: "Pool not found in mock".to_string(),
                     request: data.clone(),
                 });
             }
             
-            // Fall back to base querier for non-Osmosis queries
-            self.base.raw_query(bin_request)
+            // Return error for unhandled Osmosis queries
+            return SystemResult::Err(SystemError::InvalidRequest {
+                error: format!("Unhandled Osmosis query path: {}", path),
+                request: data.clone(),
+            });
         }
+        
+        // Fall back to base querier for non-Osmosis queries
+        self.base.raw_query(bin_request)
     }
+}
 
-    /// Create mock dependencies with Osmosis query support
-    pub fn mock_dependencies_with_osmosis() -> OwnedDeps<MockStorage, MockApi, OsmosisQuerier> {
-        let custom_querier = OsmosisQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &[])]))
-            .with_pool(1, "uatom", "uosmo", "1050000000000000000", 0.95); // 1.05 spot price, 95% swap rate
-            
-        OwnedDeps {
-            storage: MockStorage::default(),
-            api: MockApi::default(),
-            querier: custom_querier,
-            custom_query_type: std::marker::PhantomData,
-        }
+/// Create mock dependencies with Osmosis query support
+pub fn mock_dependencies_with_osmosis() -> OwnedDeps<MockStorage, MockApi, OsmosisQuerier> {
+    let custom_querier = OsmosisQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &[])]))
+        .with_pool(1, "uatom", "uosmo", "960000000000000000", 0.95); // 0.96 spot price, 95% swap rate
+        
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: custom_querier,
+        custom_query_type: std::marker::PhantomData,
     }
-}
\ No newline at end of file
+}
@@ -8,8 +8,8 @@ mod tests {
     use crate::{
         contract::{instantiate, execute},
         msg::{ExecuteMsg, InstantiateMsg},
-        dex::{SwapParams, SwapRoute, query_spot_price, estimate_swap},
-        test_helpers::test_helpers::mock_dependencies_with_osmosis,
+        dex::{Dex, SwapParams, SwapRoute, query_spot_price, estimate_swap},
+        test_helpers::mock_dependencies_with_osmosis,
     };
     
     #[test]
@@ -24,8 +24,8 @@ mod tests {
             "uosmo".to_string(),
         ).unwrap();
         
-        // Verify the spot price from our mock (1.05)
-        assert_eq!(result.spot_price, Decimal::from_atomics(1050000000000000000u128, 18).unwrap());
+        // Verify the spot price from our mock (0.96)
+        assert_eq!(result.spot_price, Decimal::from_atomics(960000000000000000u128, 18).unwrap());
         assert_eq!(result.token_in_denom, "uatom");
         assert_eq!(result.token_out_denom, "uosmo");
     }
@@ -43,6 +43,7 @@ mod tests {
         let routes = vec![SwapRoute {
             pool_id: 1,
             token_out_denom: "uosmo".to_string(),
+            dex: Dex::Osmosis,
         }];
         
         let result = estimate_swap(
@@ -58,16 +59,274 @@ mod tests {
         // Swap fee should be 0.3%
         assert_eq!(result.swap_fee, Decimal::permille(3));
     }
+
+    #[test]
+    fn test_create_htlc_with_swap_mints_claim_token() {
+        use crate::claim_token::{query_num_tokens, query_owner_of};
+
+        let mut deps = mock_dependencies_with_osmosis();
+        let env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), InstantiateMsg { admin: None }).unwrap();
+
+        let msg = ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "resolver1".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            swap_params: SwapParams {
+                routes: vec![SwapRoute {
+                    pool_id: 1,
+                    token_out_denom: "uosmo".to_string(),
+                    dex: Dex::Osmosis,
+                }],
+                min_output_amount: Uint128::new(90),
+                slippage_tolerance: Decimal::percent(5),
+                max_input_amount: None,
+            },
+            preimage_length: None,
+            transferable: Some(true),
+        };
+        let res = execute(deps.as_mut(), env, mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+        assert!(res.attributes.iter().any(|a| a.key == "claim_token_id" && a.value == "htlc_0"));
+
+        let htlc = crate::state::HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        assert!(htlc.claim_token);
+        assert_eq!(query_owner_of(deps.as_ref(), "htlc_0".to_string()).unwrap().owner, "resolver1");
+        assert_eq!(query_num_tokens(deps.as_ref()).unwrap().count, 1);
+    }
+
+    #[test]
+    fn test_multi_hop_swap_fees() {
+        use crate::test_helpers::OsmosisQuerier;
+        use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+        
+        // Balancer pool with a 0.2% fee followed by a CL pool with a 0.05% spread factor
+        let custom_querier = OsmosisQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &[])]))
+            .with_pool(1, "uatom", "uusdc", "10000000000000000000", 0.98)
+            .with_pool(2, "uusdc", "uosmo", "2000000000000000000", 0.97)
+            .with_swap_fee(1, "2000000000000000")
+            .with_spread_factor(2, "0.000500000000000000");
+            
+        let deps = cosmwasm_std::OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: custom_querier,
+            custom_query_type: std::marker::PhantomData,
+        };
+        
+        let routes = vec![
+            SwapRoute {
+                pool_id: 1,
+                token_out_denom: "uusdc".to_string(),
+                dex: Dex::Osmosis,
+            },
+            SwapRoute {
+                pool_id: 2,
+                token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
+            },
+        ];
+        
+        let result = estimate_swap(
+            deps.as_ref(),
+            cosmwasm_std::Coin::new(100, "uatom"),
+            routes,
+        ).unwrap();
+        
+        // Each hop reports its own fee, charged in the hop's input denom
+        assert_eq!(result.hop_fees.len(), 2);
+        assert_eq!(result.hop_fees[0].pool_id, 1);
+        assert_eq!(result.hop_fees[0].swap_fee, Decimal::permille(2));
+        assert_eq!(result.hop_fees[0].fee_denom, "uatom");
+        assert_eq!(result.hop_fees[1].pool_id, 2);
+        assert_eq!(result.hop_fees[1].swap_fee, Decimal::from_ratio(5u128, 10000u128));
+        assert_eq!(result.hop_fees[1].fee_denom, "uusdc");
+        
+        // Total fee compounds: 1 - (1 - 0.002) * (1 - 0.0005) = 0.002499
+        assert_eq!(result.swap_fee, Decimal::from_ratio(2499u128, 1000000u128));
+    }
+    
+    #[test]
+    fn test_multi_hop_price_impact() {
+        use crate::error::ContractError;
+        use crate::test_helpers::OsmosisQuerier;
+        use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+        
+        // Two hops at a 1.0 spot price, each returning 90% of the input
+        let custom_querier = OsmosisQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &[])]))
+            .with_pool(1, "uatom", "uusdc", "1000000000000000000", 0.9)
+            .with_pool(2, "uusdc", "uosmo", "1000000000000000000", 0.9);
+            
+        let mut deps = cosmwasm_std::OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: custom_querier,
+            custom_query_type: std::marker::PhantomData,
+        };
+        
+        let routes = vec![
+            SwapRoute {
+                pool_id: 1,
+                token_out_denom: "uusdc".to_string(),
+                dex: Dex::Osmosis,
+            },
+            SwapRoute {
+                pool_id: 2,
+                token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
+            },
+        ];
+        
+        let result = estimate_swap(
+            deps.as_ref(),
+            cosmwasm_std::Coin::new(100, "uatom"),
+            routes.clone(),
+        ).unwrap();
+        
+        // 100 uatom -> 90 uusdc -> 81 uosmo
+        assert_eq!(result.token_out_amount, Uint128::new(81));
+        assert_eq!(result.hop_price_impacts.len(), 2);
+        assert_eq!(result.hop_price_impacts[0].token_out_amount, Uint128::new(90));
+        assert_eq!(result.hop_price_impacts[0].price_impact, Decimal::from_ratio(100u128, 90u128) - Decimal::one());
+        assert_eq!(result.hop_price_impacts[1].price_impact, Decimal::from_ratio(90u128, 81u128) - Decimal::one());
+        assert_eq!(result.price_impact, Decimal::from_ratio(100u128, 81u128) - Decimal::one());
+        
+        let env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), InstantiateMsg { admin: None }).unwrap();
+        
+        let msg = ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            swap_params: SwapParams {
+                routes,
+                min_output_amount: Uint128::new(80),
+                slippage_tolerance: Decimal::percent(5),
+                max_input_amount: None,
+            },
+            preimage_length: None,
+            transferable: None,
+        };
+        
+        // ~23% impact is above the default 5% maximum
+        let err = execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg.clone()).unwrap_err();
+        match err {
+            ContractError::PriceImpactTooHigh { max, .. } => assert_eq!(max, Decimal::percent(5)),
+            _ => panic!("Expected PriceImpactTooHigh error, got {:?}", err),
+        }
+        
+        // Raising the configured maximum lets the swap through
+        let mut config = fusion_plus::ProtocolConfig::default();
+        config.swap.max_price_impact = Decimal::percent(30);
+        execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), ExecuteMsg::UpdateConfig { config }).unwrap();
+        execute(deps.as_mut(), env, mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+    }
+    
+    #[test]
+    fn test_price_impact_far_from_parity() {
+        use crate::error::ContractError;
+        use crate::test_helpers::OsmosisQuerier;
+        use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+        
+        // 1 uatom = 20 uusdc and 1 uusdc = 0.05 uosmo, each hop filling 20% below spot.
+        // Pools 3 and 4 have the same prices but fill within 1% of spot.
+        let custom_querier = OsmosisQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &[])]))
+            .with_pool(1, "uatom", "uusdc", "20.000000000000000000", 16.0)
+            .with_pool(2, "uusdc", "uosmo", "0.050000000000000000", 0.04)
+            .with_pool(3, "uatom", "uusdc", "20.000000000000000000", 19.9)
+            .with_pool(4, "uusdc", "uosmo", "0.050000000000000000", 0.0499);
+            
+        let mut deps = cosmwasm_std::OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: custom_querier,
+            custom_query_type: std::marker::PhantomData,
+        };
+        
+        let route = |pool_id: u64, token_out_denom: &str| SwapRoute {
+            pool_id,
+            token_out_denom: token_out_denom.to_string(),
+            dex: Dex::Osmosis,
+        };
+        
+        // Single hop at 20 uusdc per uatom: spot is 0.05 uatom per uusdc,
+        // and 100 uatom -> 1600 uusdc fills at 0.0625
+        let result = estimate_swap(deps.as_ref(), cosmwasm_std::Coin::new(100, "uatom"), vec![route(1, "uusdc")]).unwrap();
+        assert_eq!(result.token_out_amount, Uint128::new(1600));
+        assert_eq!(result.hop_price_impacts[0].spot_price, Decimal::percent(5));
+        assert_eq!(result.price_impact, Decimal::percent(25));
+        
+        // Single hop at 0.05 uosmo per uusdc: spot is 20 uusdc per uosmo,
+        // and 1600 uusdc -> 64 uosmo fills at 25
+        let result = estimate_swap(deps.as_ref(), cosmwasm_std::Coin::new(1600, "uusdc"), vec![route(2, "uosmo")]).unwrap();
+        assert_eq!(result.token_out_amount, Uint128::new(64));
+        assert_eq!(result.hop_price_impacts[0].spot_price, Decimal::from_ratio(20u128, 1u128));
+        assert_eq!(result.price_impact, Decimal::percent(25));
+        
+        // Both hops chain to a 1.0 route price, filled at 100 / 64
+        let routes = vec![route(1, "uusdc"), route(2, "uosmo")];
+        let result = estimate_swap(deps.as_ref(), cosmwasm_std::Coin::new(100, "uatom"), routes.clone()).unwrap();
+        assert_eq!(result.token_out_amount, Uint128::new(64));
+        assert_eq!(result.hop_price_impacts[0].price_impact, Decimal::percent(25));
+        assert_eq!(result.hop_price_impacts[1].price_impact, Decimal::percent(25));
+        assert_eq!(result.price_impact, Decimal::from_ratio(5625u128, 10000u128));
+        
+        let env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), InstantiateMsg { admin: None }).unwrap();
+        
+        let create_msg = |routes: Vec<SwapRoute>, min_output_amount: u128| ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            swap_params: SwapParams {
+                routes,
+                min_output_amount: Uint128::new(min_output_amount),
+                slippage_tolerance: Decimal::percent(5),
+                max_input_amount: None,
+            },
+            preimage_length: None,
+            transferable: None,
+        };
+        
+        // The lossy route is rejected against the default 5% maximum
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &coins(100, "uatom")),
+            create_msg(routes, 60),
+        ).unwrap_err();
+        match err {
+            ContractError::PriceImpactTooHigh { impact, .. } => {
+                assert_eq!(impact, Decimal::from_ratio(5625u128, 10000u128))
+            }
+            _ => panic!("Expected PriceImpactTooHigh error, got {:?}", err),
+        }
+        
+        // 100 uatom -> 1990 uusdc -> 99 uosmo is about 1% from spot and goes through
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &coins(100, "uatom")),
+            create_msg(vec![route(3, "uusdc"), route(4, "uosmo")], 95),
+        ).unwrap();
+        let estimated_output = res.attributes.iter().find(|a| a.key == "estimated_output").unwrap();
+        assert_eq!(estimated_output.value, "99");
+    }
     
     #[test]
     fn test_create_htlc_with_multi_hop_swap() {
-        use crate::test_helpers::test_helpers::OsmosisQuerier;
+        use crate::test_helpers::OsmosisQuerier;
         use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
         
         // Create custom querier with multiple pools
         let custom_querier = OsmosisQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &[])]))
-            .with_pool(1, "uatom", "uusdc", "10000000000000000000", 0.98) // 10.0 spot price, 98% rate
-            .with_pool(2, "uusdc", "uosmo", "2000000000000000000", 0.97); // 2.0 spot price, 97% rate
+            .with_pool(1, "uatom", "uusdc", "10000000000000000000", 9.9) // 10.0 spot price, 1% below spot
+            .with_pool(2, "uusdc", "uosmo", "2000000000000000000", 1.98); // 2.0 spot price, 1% below spot
             
         let mut deps = cosmwasm_std::OwnedDeps {
             storage: MockStorage::default(),
@@ -94,14 +353,17 @@ mod tests {
                 SwapRoute {
                     pool_id: 1,
                     token_out_denom: "uusdc".to_string(),
+                    dex: Dex::Osmosis,
                 },
                 SwapRoute {
                     pool_id: 2,
                     token_out_denom: "uosmo".to_string(),
+                    dex: Dex::Osmosis,
                 },
             ],
-            min_output_amount: Uint128::new(85), // Expecting ~95% of 100 after 2 hops
+            min_output_amount: Uint128::new(1900), // Expecting 100 * 9.9 * 1.98 = 1960 after 2 hops
             slippage_tolerance: Decimal::percent(5),
+            max_input_amount: None,
         };
         
         let msg = ExecuteMsg::CreateHtlcWithSwap {
@@ -111,6 +373,8 @@ mod tests {
             target_chain: "cosmoshub-4".to_string(),
             target_address: "cosmos1abc...".to_string(),
             swap_params: swap_params.clone(),
+            preimage_length: None,
+            transferable: None,
         };
         
         // This should succeed with multi-hop support
@@ -128,4 +392,274 @@ mod tests {
         assert_eq!(saved_params.routes[0].pool_id, 1);
         assert_eq!(saved_params.routes[1].pool_id, 2);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_twap_guard() {
+        use crate::error::ContractError;
+        use crate::msg::QueryMsg;
+        use crate::dex::TwapPriceResponse;
+        use crate::test_helpers::OsmosisQuerier;
+        use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+        
+        // Spot price 1.05 against a TWAP of 1.0
+        let custom_querier = OsmosisQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &[])]))
+            .with_pool(1, "uatom", "uosmo", "1050000000000000000", 1.04)
+            .with_twap(1, "uatom", "uosmo", "1.000000000000000000");
+            
+        let mut deps = cosmwasm_std::OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: custom_querier,
+            custom_query_type: std::marker::PhantomData,
+        };
+        
+        let env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), InstantiateMsg { admin: None }).unwrap();
+        
+        // The query reports both prices over the configured window
+        let res = crate::contract::query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::QueryTwapPrice {
+                pool_id: 1,
+                base_denom: "uatom".to_string(),
+                quote_denom: "uosmo".to_string(),
+                window: None,
+            },
+        ).unwrap();
+        let price: TwapPriceResponse = cosmwasm_std::from_json(res).unwrap();
+        assert_eq!(price.spot_price, Decimal::from_ratio(105u128, 100u128));
+        assert_eq!(price.twap_price, Decimal::one());
+        assert_eq!(price.window, 1800);
+        assert_eq!(price.deviation, Decimal::percent(5));
+        
+        let mut config = fusion_plus::ProtocolConfig::default();
+        config.swap.max_twap_deviation = Decimal::percent(2);
+        execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), ExecuteMsg::UpdateConfig { config }).unwrap();
+        
+        let msg = ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            swap_params: SwapParams {
+                routes: vec![SwapRoute {
+                    pool_id: 1,
+                    token_out_denom: "uosmo".to_string(),
+                    dex: Dex::Osmosis,
+                }],
+                min_output_amount: Uint128::new(90),
+                slippage_tolerance: Decimal::percent(5),
+                max_input_amount: None,
+            },
+            preimage_length: None,
+            transferable: None,
+        };
+        
+        // A 5% deviation is rejected when at most 2% is allowed
+        let err = execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), msg.clone()).unwrap_err();
+        match err {
+            ContractError::TwapDeviationTooHigh { pool_id, deviation, .. } => {
+                assert_eq!(pool_id, 1);
+                assert_eq!(deviation, Decimal::percent(5));
+            }
+            _ => panic!("Expected TwapDeviationTooHigh error, got {:?}", err),
+        }
+        
+        // And accepted within the default 5% threshold
+        let config = fusion_plus::ProtocolConfig::default();
+        execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), ExecuteMsg::UpdateConfig { config }).unwrap();
+        execute(deps.as_mut(), env, mock_info("sender", &coins(100, "uatom")), msg).unwrap();
+    }
+    
+    #[test]
+    fn test_exact_out_swap_refunds_leftover_input() {
+        use crate::contract::reply;
+        use crate::error::ContractError;
+        use crate::test_helpers::mock_dependencies_with_osmosis;
+        use cosmwasm_std::{BankMsg, CosmosMsg, Reply, SubMsgResponse, SubMsgResult};
+        use osmosis_std::types::osmosis::poolmanager::v1beta1::MsgSwapExactAmountOutResponse;
+        
+        let mut deps = mock_dependencies_with_osmosis();
+        let env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), InstantiateMsg { admin: None }).unwrap();
+        
+        // Buy exactly 76 uosmo, which takes 80 uatom at the mocked 95% rate
+        let mut swap_params = SwapParams {
+            routes: vec![SwapRoute {
+                pool_id: 1,
+                token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
+            }],
+            min_output_amount: Uint128::new(76),
+            slippage_tolerance: Decimal::percent(1),
+            max_input_amount: Some(Uint128::new(70)),
+        };
+        let create_msg = |swap_params: SwapParams| ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: "a".repeat(64),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            swap_params,
+            preimage_length: None,
+            transferable: None,
+        };
+        
+        // A maximum below the required input is rejected
+        let err = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &coins(100, "uatom")),
+            create_msg(swap_params.clone()),
+        ).unwrap_err();
+        match err {
+            ContractError::ExcessiveInputAmount {} => {}
+            _ => panic!("Expected ExcessiveInputAmount error, got {:?}", err),
+        }
+        
+        swap_params.max_input_amount = Some(Uint128::new(90));
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("sender", &coins(100, "uatom")),
+            create_msg(swap_params.clone()),
+        ).unwrap();
+        let estimated_input = res.attributes.iter().find(|a| a.key == "estimated_input").unwrap();
+        assert_eq!(estimated_input.value, "80");
+        
+        // Execution sends an exact-out swap capped at the maximum input and awaits its reply
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_0".to_string(),
+            swap_params,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("sender", &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, 2);
+        
+        // The swap spent 80 of the 100 uatom deposited
+        let swap_response = MsgSwapExactAmountOutResponse {
+            token_in_amount: "80".to_string(),
+        };
+        let res = reply(
+            deps.as_mut(),
+            env,
+            Reply {
+                id: 2,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(swap_response.into()),
+                }),
+            },
+        ).unwrap();
+        
+        match &res.messages[0].msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                assert_eq!(to_address, "sender");
+                assert_eq!(amount, &coins(20, "uatom"));
+            }
+            _ => panic!("Expected bank send message"),
+        }
+        
+        // The HTLC now locks the bought amount
+        let htlc = crate::state::HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        assert_eq!(htlc.amount, coins(76, "uosmo"));
+    }    
+    #[test]
+    fn test_exact_in_swap_locks_received_output() {
+        use crate::contract::reply;
+        use crate::error::ContractError;
+        use crate::test_helpers::mock_dependencies_with_osmosis;
+        use cosmwasm_std::testing::MOCK_CONTRACT_ADDR;
+        use cosmwasm_std::{BankMsg, CosmosMsg, Reply, SubMsgResponse, SubMsgResult};
+        
+        let mut deps = mock_dependencies_with_osmosis();
+        let mut env = mock_env();
+        instantiate(deps.as_mut(), env.clone(), mock_info("admin", &[]), InstantiateMsg { admin: None }).unwrap();
+        
+        let secret = [1u8; 32];
+        let swap_params = SwapParams {
+            routes: vec![SwapRoute {
+                pool_id: 1,
+                token_out_denom: "uosmo".to_string(),
+                dex: Dex::Osmosis,
+            }],
+            min_output_amount: Uint128::new(90),
+            slippage_tolerance: Decimal::percent(1),
+            max_input_amount: None,
+        };
+        let create_msg = ExecuteMsg::CreateHtlcWithSwap {
+            receiver: "receiver".to_string(),
+            hashlock: hex::encode(sha2::Sha256::digest(secret)),
+            timelock: env.block.time.seconds() + 3600,
+            target_chain: "cosmoshub-4".to_string(),
+            target_address: "cosmos1abc...".to_string(),
+            swap_params: swap_params.clone(),
+            preimage_length: None,
+            transferable: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), create_msg.clone()).unwrap();
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &coins(100, "uatom")), create_msg).unwrap();
+        
+        // The contract already holds 30 uosmo for other HTLCs
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(30, "uosmo"));
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_0".to_string(),
+            swap_params: swap_params.clone(),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("sender", &[]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, 3);
+        
+        // The swap filled 96 uosmo, above the 94 minimum
+        deps.querier.update_balance(MOCK_CONTRACT_ADDR, coins(126, "uosmo"));
+        let res = reply(
+            deps.as_mut(),
+            env.clone(),
+            Reply {
+                id: 3,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: None,
+                }),
+            },
+        ).unwrap();
+        let token_out = res.attributes.iter().find(|a| a.key == "token_out_amount").unwrap();
+        assert_eq!(token_out.value, "96");
+        
+        // Withdrawal pays out the swapped amount rather than the deposit
+        let htlc = crate::state::HTLCS.load(&deps.storage, "htlc_0").unwrap();
+        assert_eq!(htlc.amount, coins(96, "uosmo"));
+        let msg = ExecuteMsg::Withdraw {
+            htlc_id: "htlc_0".to_string(),
+            secret: hex::encode(secret),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("receiver", &[]), msg).unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Bank(BankMsg::Send { amount, .. }) => assert_eq!(amount, &coins(96, "uosmo")),
+            _ => panic!("Expected bank send message"),
+        }
+        
+        // Expired HTLCs keep their deposit so they can be refunded as is
+        env.block.time = env.block.time.plus_seconds(3600);
+        let msg = ExecuteMsg::ExecuteSwapAndLock {
+            htlc_id: "htlc_1".to_string(),
+            swap_params,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("sender", &[]), msg.clone()).unwrap_err();
+        match err {
+            ContractError::TimelockExpired {} => {}
+            _ => panic!("Expected TimelockExpired error, got {:?}", err),
+        }
+        
+        // And refunded ones no longer hold it
+        let refund = ExecuteMsg::Refund { htlc_id: "htlc_1".to_string() };
+        execute(deps.as_mut(), env.clone(), mock_info("sender", &[]), refund).unwrap();
+        let err = execute(deps.as_mut(), env, mock_info("sender", &[]), msg).unwrap_err();
+        match err {
+            ContractError::AlreadyRefunded {} => {}
+            _ => panic!("Expected AlreadyRefunded error, got {:?}", err),
+        }
+    }
+}
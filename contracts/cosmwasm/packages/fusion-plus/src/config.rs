@@ -1,4 +1,4 @@
-use cosmwasm_std::Uint128;
+use cosmwasm_std::{Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -13,6 +13,44 @@ pub struct ProtocolConfig {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct SwapConfig {
     pub timelock: TimelockConfig,
+    /// Maximum price impact accepted when creating an HTLC with a swap (default: 5%)
+    #[serde(default = "default_max_price_impact")]
+    pub max_price_impact: Decimal,
+    /// Window in seconds of the TWAP that spot prices are checked against (default: 30 minutes)
+    #[serde(default = "default_twap_window")]
+    pub twap_window: u64,
+    /// Maximum deviation of the spot price from the TWAP (default: 5%)
+    #[serde(default = "default_max_twap_deviation")]
+    pub max_twap_deviation: Decimal,
+    /// DEX that swaps are routed through (default: Osmosis)
+    #[serde(default)]
+    pub dex: DexConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DexConfig {
+    /// Osmosis poolmanager
+    #[default]
+    Osmosis,
+    /// Astroport router and factory contracts, as deployed on Neutron and Terra
+    Astroport { router: String, factory: String },
+}
+
+fn default_max_price_impact() -> Decimal {
+    Decimal::percent(5)
+}
+
+fn default_twap_window() -> u64 {
+    1800
+}
+
+fn default_max_twap_deviation() -> Decimal {
+    Decimal::percent(5)
+}
+
+fn default_max_pool_staleness() -> u64 {
+    3600
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -39,8 +77,12 @@ pub struct RoutingConfig {
     pub max_routes_to_explore: u32,
     /// Minimal amount for routing
     pub minimal_amount: Uint128,
-    /// Pool discovery range
+    /// DEPRECATED: pool discovery range. Routing now finds pools through the
+    /// router's denom index, with no range limit.
     pub pool_discovery_range: PoolDiscoveryRange,
+    /// Age in seconds past which pool data is too stale to route through (default: 1 hour)
+    #[serde(default = "default_max_pool_staleness")]
+    pub max_pool_staleness: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -79,6 +121,10 @@ impl Default for ProtocolConfig {
                         final_hop: 21600,      // 6 hours
                     },
                 },
+                max_price_impact: default_max_price_impact(),
+                twap_window: default_twap_window(),
+                max_twap_deviation: default_max_twap_deviation(),
+                dex: DexConfig::Osmosis,
             },
             routing: RoutingConfig {
                 max_hops: 4,
@@ -88,6 +134,7 @@ impl Default for ProtocolConfig {
                     start: 1,
                     end: 1000,
                 },
+                max_pool_staleness: default_max_pool_staleness(),
             },
             chains: vec![
                 ChainConfig {
@@ -177,7 +224,7 @@ impl ProtocolConfig {
 
     /// Create configuration with optional override
     pub fn with_override(override_config: Option<ProtocolConfig>) -> Self {
-        override_config.unwrap_or_else(|| Self::default())
+        override_config.unwrap_or_default()
     }
 
     /// Validate configuration values
@@ -201,6 +248,24 @@ impl ProtocolConfig {
             ));
         }
 
+        if self.swap.max_price_impact > cosmwasm_std::Decimal::one() {
+            return Err(cosmwasm_std::StdError::generic_err(
+                "Max price impact cannot exceed 100%"
+            ));
+        }
+
+        if self.swap.twap_window == 0 {
+            return Err(cosmwasm_std::StdError::generic_err(
+                "TWAP window must be greater than zero"
+            ));
+        }
+
+        if self.swap.max_twap_deviation > cosmwasm_std::Decimal::one() {
+            return Err(cosmwasm_std::StdError::generic_err(
+                "Max TWAP deviation cannot exceed 100%"
+            ));
+        }
+
         // Validate routing parameters
         if self.routing.max_hops == 0 || self.routing.max_hops > 10 {
             return Err(cosmwasm_std::StdError::generic_err(
@@ -214,6 +279,12 @@ impl ProtocolConfig {
             ));
         }
 
+        if self.routing.max_pool_staleness == 0 {
+            return Err(cosmwasm_std::StdError::generic_err(
+                "Max pool staleness must be greater than zero"
+            ));
+        }
+
         // Validate pool discovery range
         if self.routing.pool_discovery_range.start >= self.routing.pool_discovery_range.end {
             return Err(cosmwasm_std::StdError::generic_err(
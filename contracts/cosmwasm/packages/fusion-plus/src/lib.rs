@@ -1,7 +1,9 @@
 pub mod config;
 pub mod msg;
+pub mod osmosis;
 pub mod state;
 
 pub use config::*;
 pub use msg::*;
+pub use osmosis::*;
 pub use state::*;
\ No newline at end of file
@@ -0,0 +1,67 @@
+use std::str::FromStr;
+
+use cosmwasm_std::{Decimal, Decimal256, StdError, StdResult, Uint256};
+
+/// Osmosis pool type URLs returned by the poolmanager Pool query
+pub const BALANCER_POOL_TYPE_URL: &str = "/osmosis.gamm.v1beta1.Pool";
+pub const STABLESWAP_POOL_TYPE_URL: &str = "/osmosis.gamm.poolmodels.stableswap.v1beta1.Pool";
+pub const CONCENTRATED_POOL_TYPE_URL: &str = "/osmosis.concentratedliquidity.v1beta1.Pool";
+
+/// Decimal places of Osmosis `Dec` values encoded as atomics
+pub const OSMOSIS_DEC_PRECISION: u32 = 18;
+
+/// Decimal places of Osmosis `BigDec` values, such as concentrated pool square root prices
+pub const OSMOSIS_BIGDEC_PRECISION: u32 = 36;
+
+/// Parse an Osmosis decimal, given either in decimal notation or as 18-decimal atomics
+pub fn parse_osmosis_dec(value: &str) -> StdResult<Decimal> {
+    if value.contains('.') {
+        return Decimal::from_str(value);
+    }
+
+    Decimal::from_atomics(
+        value.parse::<u128>()
+            .map_err(|e| StdError::generic_err(format!("Failed to parse decimal: {}", e)))?,
+        OSMOSIS_DEC_PRECISION,
+    ).map_err(|e| StdError::generic_err(format!("Failed to create decimal: {}", e)))
+}
+
+/// Parse an Osmosis decimal with `precision` places, given either in decimal notation
+/// or as atomics, dropping the places past the 18 a `Decimal256` holds
+pub fn parse_osmosis_dec256(value: &str, precision: u32) -> StdResult<Decimal256> {
+    if let Some((whole, fraction)) = value.split_once('.') {
+        let fraction = &fraction[..fraction.len().min(Decimal256::DECIMAL_PLACES as usize)];
+        return Decimal256::from_str(&format!("{}.{}", whole, fraction));
+    }
+
+    let atomics = Uint256::from_str(value)?;
+    let excess = precision.saturating_sub(Decimal256::DECIMAL_PLACES);
+    Decimal256::from_atomics(
+        atomics / Uint256::from(10u8).pow(excess),
+        precision.min(Decimal256::DECIMAL_PLACES),
+    ).map_err(|e| StdError::generic_err(format!("Failed to create decimal: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_osmosis_dec() {
+        assert_eq!(parse_osmosis_dec("3000000000000000").unwrap(), Decimal::permille(3));
+        assert_eq!(parse_osmosis_dec("0.003000000000000000").unwrap(), Decimal::permille(3));
+        assert!(parse_osmosis_dec("abc").is_err());
+    }
+
+    #[test]
+    fn test_parse_osmosis_dec256() {
+        // BigDec atomics keep the first 18 of their 36 places
+        let sqrt_price = parse_osmosis_dec256("1414213562373095048801688724209698079", OSMOSIS_BIGDEC_PRECISION);
+        assert_eq!(sqrt_price.unwrap(), Decimal256::from_str("1.414213562373095048").unwrap());
+        let sqrt_price = parse_osmosis_dec256("1.414213562373095048801688724209698079", OSMOSIS_BIGDEC_PRECISION);
+        assert_eq!(sqrt_price.unwrap(), Decimal256::from_str("1.414213562373095048").unwrap());
+
+        let liquidity = parse_osmosis_dec256("2500000000000000000000", OSMOSIS_DEC_PRECISION);
+        assert_eq!(liquidity.unwrap(), Decimal256::from_str("2500").unwrap());
+    }
+}
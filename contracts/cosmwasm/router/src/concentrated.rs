@@ -0,0 +1,246 @@
+//! Concentrated-liquidity swap simulation over a pool snapshot.
+//!
+//! Prices are of the pool's second token in terms of its first (token1 per
+//! token0), so swapping token0 in moves the price down through lower ticks and
+//! swapping token1 in moves it up. Within a tick range with liquidity `L`:
+//!
+//! ```text
+//! token0 in:  √P' = L·√P / (L + Δx·√P)     token1 out = L·(√P - √P')
+//! token1 in:  √P' = √P + Δy / L            token0 out = L·(√P' - √P) / (√P·√P')
+//! ```
+//!
+//! Crossing an initialized tick moving up adds its liquidity net; moving down
+//! subtracts it. Ticks map to prices the way Osmosis does: 9,000,000 ticks per
+//! power of ten, starting at increments of 10⁻⁶ at price one.
+
+use cosmwasm_std::{Decimal256, SignedDecimal256, StdError, StdResult, Uint128, Uint256};
+
+use crate::msg::ClSnapshot;
+
+/// Ticks between consecutive powers of ten in price
+const TICKS_PER_DECADE: i64 = 9_000_000;
+
+/// Price increment exponent at price one
+const EXPONENT_AT_PRICE_ONE: i64 = -6;
+
+/// Amount out for `amount_in` of token0 (`zero_for_one`) or token1, before fees.
+///
+/// Input the snapshot has no liquidity for is left unswapped, so the result
+/// only counts what the known ranges can fill.
+pub fn swap_output(snapshot: &ClSnapshot, zero_for_one: bool, amount_in: Uint128) -> StdResult<Uint128> {
+    let mut remaining = Decimal256::from_ratio(amount_in, 1u8);
+    let mut sqrt_price = snapshot.current_sqrt_price;
+    let mut liquidity = snapshot.current_liquidity;
+    let mut amount_out = Decimal256::zero();
+
+    if sqrt_price.is_zero() {
+        return Err(StdError::generic_err("Concentrated liquidity snapshot has a zero price"));
+    }
+
+    // Ticks to cross, nearest first
+    let mut ticks: Vec<_> = snapshot
+        .ticks
+        .iter()
+        .filter(|tick| {
+            if zero_for_one {
+                tick.tick_index <= snapshot.current_tick
+            } else {
+                tick.tick_index > snapshot.current_tick
+            }
+        })
+        .collect();
+    if zero_for_one {
+        ticks.sort_by_key(|tick| std::cmp::Reverse(tick.tick_index));
+    } else {
+        ticks.sort_by_key(|tick| tick.tick_index);
+    }
+
+    for tick in ticks {
+        let target = tick_to_price(tick.tick_index)?.sqrt();
+
+        if !liquidity.is_zero() {
+            // Input that moves the price all the way to the tick
+            let to_target = if zero_for_one {
+                liquidity * (sqrt_price - target) / sqrt_price / target
+            } else {
+                liquidity * (target - sqrt_price)
+            };
+
+            if remaining < to_target {
+                amount_out += swap_within_range(liquidity, sqrt_price, remaining, zero_for_one);
+                remaining = Decimal256::zero();
+                break;
+            }
+
+            amount_out += if zero_for_one {
+                liquidity * (sqrt_price - target)
+            } else {
+                liquidity * (target - sqrt_price) / sqrt_price / target
+            };
+            remaining -= to_target;
+        }
+
+        sqrt_price = target;
+        liquidity = apply_liquidity_net(liquidity, tick.liquidity_net, !zero_for_one)?;
+    }
+
+    // Past the last known tick the current range extends indefinitely
+    if !remaining.is_zero() && !liquidity.is_zero() {
+        amount_out += swap_within_range(liquidity, sqrt_price, remaining, zero_for_one);
+    }
+
+    Ok(amount_out.to_uint_floor().try_into()?)
+}
+
+/// Output for an amount that stays inside a single liquidity range
+fn swap_within_range(
+    liquidity: Decimal256,
+    sqrt_price: Decimal256,
+    amount_in: Decimal256,
+    zero_for_one: bool,
+) -> Decimal256 {
+    if zero_for_one {
+        let next = liquidity * sqrt_price / (liquidity + amount_in * sqrt_price);
+        liquidity * (sqrt_price - next)
+    } else {
+        let next = sqrt_price + amount_in / liquidity;
+        liquidity * (next - sqrt_price) / sqrt_price / next
+    }
+}
+
+/// Add (`upward`) or subtract a tick's liquidity net from the active liquidity
+fn apply_liquidity_net(
+    liquidity: Decimal256,
+    net: SignedDecimal256,
+    upward: bool,
+) -> StdResult<Decimal256> {
+    let magnitude = net.abs_diff(SignedDecimal256::zero());
+    let adds = net.is_negative() != upward;
+    if adds {
+        Ok(liquidity.checked_add(magnitude)?)
+    } else {
+        liquidity
+            .checked_sub(magnitude)
+            .map_err(|_| StdError::generic_err("Concentrated liquidity snapshot has negative liquidity"))
+    }
+}
+
+/// Price at a tick, following Osmosis' tick spacing
+pub fn tick_to_price(tick_index: i64) -> StdResult<Decimal256> {
+    if tick_index == 0 {
+        return Ok(Decimal256::one());
+    }
+
+    // Truncating division, so negative ticks step down from price one
+    let decade = tick_index / TICKS_PER_DECADE;
+    let mut exponent = EXPONENT_AT_PRICE_ONE + decade;
+    if tick_index < 0 {
+        exponent -= 1;
+    }
+    let additive_ticks = tick_index - decade * TICKS_PER_DECADE;
+
+    let base = pow10(decade)?;
+    let offset = Decimal256::from_ratio(additive_ticks.unsigned_abs(), 1u8) * pow10(exponent)?;
+    if additive_ticks < 0 {
+        Ok(base.checked_sub(offset)?)
+    } else {
+        Ok(base.checked_add(offset)?)
+    }
+}
+
+fn pow10(exponent: i64) -> StdResult<Decimal256> {
+    if exponent >= 0 {
+        let power = Uint256::from(10u8).checked_pow(exponent as u32)?;
+        Decimal256::checked_from_ratio(power, 1u8)
+            .map_err(|_| StdError::generic_err("Tick is outside the supported price range"))
+    } else {
+        Decimal256::from_atomics(1u8, exponent.unsigned_abs() as u32)
+            .map_err(|_| StdError::generic_err("Tick is outside the supported price range"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::msg::TickLiquidity;
+
+    fn tick(tick_index: i64, liquidity_net: &str) -> TickLiquidity {
+        TickLiquidity {
+            tick_index,
+            liquidity_net: SignedDecimal256::from_str(liquidity_net).unwrap(),
+        }
+    }
+
+    /// Price one, with positions on [-2M, 1M) of 500 and [-1M, 1M) of 1000
+    fn snapshot() -> ClSnapshot {
+        ClSnapshot {
+            current_sqrt_price: Decimal256::one(),
+            current_tick: 0,
+            current_liquidity: Decimal256::from_ratio(1_500u128, 1u8),
+            ticks: vec![
+                tick(1_000_000, "-1500"),
+                tick(-2_000_000, "500"),
+                tick(-1_000_000, "1000"),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_tick_to_price() {
+        let cases = [
+            (-1, "0.9999999"),
+            (1_000_000, "2"),
+            (-1_000_000, "0.9"),
+            (9_000_000, "10"),
+            (-9_000_000, "0.1"),
+            (18_000_000, "100"),
+            (-18_000_001, "0.009999999"),
+        ];
+        for (tick_index, price) in cases {
+            assert_eq!(tick_to_price(tick_index).unwrap(), Decimal256::from_str(price).unwrap());
+        }
+    }
+
+    // Expected values are the exact swap math in 80-digit decimals, rounded down
+    #[test]
+    fn test_swap_output() {
+        let snapshot = snapshot();
+        let cases = [
+            // Inside the current range
+            (true, 50, 48),
+            (false, 100, 93),
+            // Crossing tick -1M, where liquidity drops to 500
+            (true, 100, 93),
+            // Crossing every tick below; the rest of the input finds no liquidity
+            (true, 10_000, 104),
+            // Crossing tick 1M, above which there is no liquidity
+            (false, 1_000, 439),
+        ];
+        for (zero_for_one, amount_in, expected) in cases {
+            let out = swap_output(&snapshot, zero_for_one, Uint128::new(amount_in)).unwrap();
+            assert_eq!(out, Uint128::new(expected), "zero_for_one {} amount {}", zero_for_one, amount_in);
+        }
+    }
+
+    #[test]
+    fn test_swap_output_full_range() {
+        // Matches constant product with reserves L/√P and L·√P
+        let snapshot = ClSnapshot {
+            current_sqrt_price: Decimal256::one(),
+            current_tick: 0,
+            current_liquidity: Decimal256::from_ratio(1_000_000u128, 1u8),
+            ticks: vec![tick(-9_000_000, "1000000"), tick(9_000_000, "-1000000")],
+        };
+        let out = swap_output(&snapshot, true, Uint128::new(1_000)).unwrap();
+        assert_eq!(out, Uint128::new(999));
+    }
+
+    #[test]
+    fn test_swap_output_rejects_negative_liquidity() {
+        let mut snapshot = snapshot();
+        snapshot.ticks = vec![tick(-1_000_000, "2000")];
+        assert!(swap_output(&snapshot, true, Uint128::new(1_000)).is_err());
+    }
+}
@@ -14,12 +14,20 @@ pub const MAX_ROUTES_TO_EXPLORE: usize = 100;
 #[deprecated(note = "Use dynamic IBC channel discovery from chain registry")]
 pub const DEFAULT_IBC_CHANNEL: &str = "channel-0";
 
+/// Number of ranked routes returned by route finding
+pub const MAX_ROUTES_RETURNED: usize = 5;
+
+/// Granularity, in percent of `amount_in`, of split route plans
+pub const SPLIT_STEP_PERCENT: u8 = 5;
+
+/// Most pools refreshed by a single `RefreshPools`
+pub const MAX_POOLS_PER_REFRESH: usize = 30;
+
 /// IBC transfer timeout in seconds (still used for IBC operations)
 pub const IBC_TRANSFER_TIMEOUT: u64 = 600; // 10 minutes
 
 /// IBC timeout buffer in seconds (buffer before overall timeout expires)
 pub const IBC_TIMEOUT_BUFFER: u64 = 300; // 5 minutes
 
-// DEPRECATED: Use config.routing.pool_discovery_range instead
-// Note: These constants have been removed as they are no longer used.
-// Pool discovery now uses dynamic configuration loaded from protocol config.
\ No newline at end of file
+// DEPRECATED: Pool discovery range constants have been removed.
+// Pool discovery now uses the denom index in state (POOL_DENOMS).
\ No newline at end of file
@@ -1,19 +1,32 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo,
+    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply,
     Response, StdResult, StdError, CosmosMsg, IbcMsg, IbcTimeout, Timestamp,
-    Uint128, Decimal,
+    Uint128, Decimal, Coin, Empty, SubMsg,
 };
 use cw2::set_contract_version;
+use cw_storage_plus::Bound;
+use osmosis_std::types::ibc::applications::transfer::v1::MsgTransfer;
+use std::collections::HashSet;
 
 use crate::error::ContractError;
+use crate::ibc;
 use crate::msg::{
-    ChainConfig, ChainConfigResponse, ConfigResponse, EstimateResponse, ExecuteMsg,
-    HopRoute, InstantiateMsg, PoolInfo, PoolInfoResponse, QueryMsg, RouteResponse,
+    ChainConfig, ChainConfigResponse, ClSnapshot, ConfigResponse, EstimateResponse, ExecuteMsg,
+    ForwardInstruction, HopRoute, InFlightSwap, InFlightSwapResponse, InstantiateMsg,
+    IBCLifecycleComplete, ListPoolsResponse, PoolInfo, PoolInfoResponse, PoolType, PoolUpdatersResponse,
+    QueryMsg, RouteResponse, RoutingMode, SplitLeg, SudoMsg, SwapInstruction, SwapStatus,
 };
-use crate::routing::{calculate_price_impact, estimate_multi_hop_swap, find_best_routes};
-use crate::state::{Config, CONFIG, CHAIN_CONFIGS, POOL_PAIRS, POOL_REGISTRY, ROUTER_REGISTRY, 
-                  save_protocol_config};
-use crate::constants::{IBC_TIMEOUT_BUFFER};
+use crate::osmosis;
+use crate::pfm::{ForwardMemo, Memo, Slippage, SwapMsg, TransferMemo, WasmMemo, ON_FAILED_DELIVERY};
+use crate::routing::{
+    calculate_price_impact, estimate_multi_hop_swap, find_best_routes, find_split_plan, is_pool_fresh,
+    split_amounts,
+};
+use crate::state::{Config, CONFIG, CHAIN_CONFIGS, IN_FLIGHT_SWAPS, PENDING_PACKETS, POOL_DENOMS, POOL_REGISTRY,
+                  POOL_UPDATERS, ROUTER_REGISTRY, CROSSCHAIN_SWAPS_REGISTRY,
+                  index_pool_denoms, index_pool_pairs, load_protocol_config, save_protocol_config,
+                  unindex_pool_denoms, unindex_pool_pairs};
+use crate::constants::{IBC_TIMEOUT_BUFFER, MAX_POOLS_PER_REFRESH};
 use fusion_plus::ProtocolConfig;
 
 const CONTRACT_NAME: &str = "crates.io:fusion-router";
@@ -69,21 +82,45 @@ pub fn execute(
         ExecuteMsg::UpdateChainConfig { chain_id, config } => {
             update_chain_config(deps, info, chain_id, config)
         }
-        ExecuteMsg::RegisterPool { pool_info } => register_pool(deps, info, pool_info),
+        ExecuteMsg::RegisterPool { pool_info } => register_pool(deps, env, info, pool_info),
         ExecuteMsg::UpdatePoolInfo { pool_id, pool_info } => {
-            update_pool_info(deps, info, pool_id, pool_info)
+            update_pool_info(deps, env, info, pool_id, pool_info)
+        }
+        ExecuteMsg::RemovePool { pool_id } => remove_pool(deps, info, pool_id),
+        ExecuteMsg::SetPoolActive { pool_id, active } => {
+            set_pool_active(deps, info, pool_id, active)
+        }
+        ExecuteMsg::RefreshPool { pool_id } => refresh_pools(deps, env, vec![pool_id]),
+        ExecuteMsg::RefreshPools { ids } => refresh_pools(deps, env, ids),
+        ExecuteMsg::AddPoolUpdater { address } => add_pool_updater(deps, info, address),
+        ExecuteMsg::RemovePoolUpdater { address } => remove_pool_updater(deps, info, address),
+        ExecuteMsg::UpdatePoolLiquidity { pool_id, liquidity, cl_snapshot, scaling_factors } => {
+            update_pool_liquidity(deps, env, info, pool_id, liquidity, cl_snapshot, scaling_factors)
+        }
+        ExecuteMsg::UpdateMaxPoolStaleness { max_pool_staleness } => {
+            update_max_pool_staleness(deps, info, max_pool_staleness)
         }
         ExecuteMsg::ExecuteMultiHopSwap {
             routes,
             min_output,
             timeout_timestamp,
-        } => execute_multi_hop_swap(deps, env, info, routes, min_output, timeout_timestamp),
+            split,
+            receiver,
+        } => match split {
+            Some(legs) => {
+                execute_split_swap(deps, env, info, routes, legs, min_output, timeout_timestamp, receiver)
+            }
+            None => execute_multi_hop_swap(deps, env, info, routes, min_output, timeout_timestamp, receiver),
+        },
         ExecuteMsg::RegisterRouter { chain_id, router_address } => {
             register_router(deps, info, chain_id, router_address)
         }
         ExecuteMsg::RemoveRouter { chain_id } => {
             remove_router(deps, info, chain_id)
         }
+        ExecuteMsg::RegisterCrosschainSwaps { chain_id, contract_address } => {
+            register_crosschain_swaps(deps, info, chain_id, contract_address)
+        }
         ExecuteMsg::UpdateRegistryContract { registry_contract } => {
             update_registry_contract(deps, info, registry_contract)
         }
@@ -111,8 +148,9 @@ fn update_chain_config(
 
 fn register_pool(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    pool_info: PoolInfo,
+    mut pool_info: PoolInfo,
 ) -> Result<Response, ContractError> {
     // Only admin can register pools
     let config = CONFIG.load(deps.storage)?;
@@ -127,35 +165,13 @@ fn register_pool(
         });
     }
 
-    // Validate pool info
-    if pool_info.token_denoms.len() < 2 {
-        return Err(ContractError::InvalidPoolConfig {});
-    }
+    validate_pool_info(&pool_info)?;
+    pool_info.last_updated = Some(env.block.time.seconds());
 
-    // Save pool info
+    // Save pool info and index it by denom and by pair
     POOL_REGISTRY.save(deps.storage, pool_info.pool_id, &pool_info)?;
-
-    // Update pool pairs index
-    for i in 0..pool_info.token_denoms.len() {
-        for j in i + 1..pool_info.token_denoms.len() {
-            let denom1 = &pool_info.token_denoms[i];
-            let denom2 = &pool_info.token_denoms[j];
-            
-            // Store both orderings for easy lookup
-            let key1 = (denom1.as_str(), denom2.as_str());
-            let key2 = (denom2.as_str(), denom1.as_str());
-            
-            // Add pool to existing pools for this pair
-            let mut pools1 = POOL_PAIRS.may_load(deps.storage, key1)?.unwrap_or_default();
-            let mut pools2 = POOL_PAIRS.may_load(deps.storage, key2)?.unwrap_or_default();
-            
-            pools1.push(pool_info.pool_id);
-            pools2.push(pool_info.pool_id);
-            
-            POOL_PAIRS.save(deps.storage, key1, &pools1)?;
-            POOL_PAIRS.save(deps.storage, key2, &pools2)?;
-        }
-    }
+    index_pool_denoms(deps.storage, &pool_info)?;
+    index_pool_pairs(deps.storage, &pool_info)?;
 
     Ok(Response::new()
         .add_attribute("method", "register_pool")
@@ -165,9 +181,10 @@ fn register_pool(
 
 fn update_pool_info(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     pool_id: u64,
-    pool_info: PoolInfo,
+    mut pool_info: PoolInfo,
 ) -> Result<Response, ContractError> {
     // Only admin can update pool info
     let config = CONFIG.load(deps.storage)?;
@@ -176,23 +193,278 @@ fn update_pool_info(
     }
 
     // Check if pool exists
-    if POOL_REGISTRY.may_load(deps.storage, pool_id)?.is_none() {
-        return Err(ContractError::PoolNotFound { pool_id });
-    }
+    let existing = POOL_REGISTRY
+        .may_load(deps.storage, pool_id)?
+        .ok_or(ContractError::PoolNotFound { pool_id })?;
 
-    // Validate pool info
-    if pool_info.token_denoms.len() < 2 || pool_info.pool_id != pool_id {
+    if pool_info.pool_id != pool_id {
         return Err(ContractError::InvalidPoolConfig {});
     }
+    validate_pool_info(&pool_info)?;
+    pool_info.last_updated = Some(env.block.time.seconds());
 
-    // Update pool info
+    // Update pool info, re-indexing in case its denoms changed
     POOL_REGISTRY.save(deps.storage, pool_id, &pool_info)?;
+    unindex_pool_denoms(deps.storage, &existing);
+    unindex_pool_pairs(deps.storage, &existing)?;
+    index_pool_denoms(deps.storage, &pool_info)?;
+    index_pool_pairs(deps.storage, &pool_info)?;
 
     Ok(Response::new()
         .add_attribute("method", "update_pool_info")
         .add_attribute("pool_id", pool_id.to_string()))
 }
 
+/// Reload pools from the local Osmosis poolmanager. Permissionless: the data
+/// comes from the chain, not the caller.
+fn refresh_pools(deps: DepsMut, env: Env, pool_ids: Vec<u64>) -> Result<Response, ContractError> {
+    if pool_ids.is_empty() || pool_ids.len() > MAX_POOLS_PER_REFRESH {
+        return Err(ContractError::InvalidInput {
+            msg: format!("Refresh between 1 and {} pools", MAX_POOLS_PER_REFRESH),
+        });
+    }
+
+    for pool_id in &pool_ids {
+        let pool_info = POOL_REGISTRY
+            .may_load(deps.storage, *pool_id)?
+            .ok_or(ContractError::PoolNotFound { pool_id: *pool_id })?;
+
+        // Only pools on this chain can be queried
+        if pool_info.chain_id != env.block.chain_id {
+            return Err(ContractError::InvalidInput {
+                msg: format!("Pool {} is on {}, not {}", pool_id, pool_info.chain_id, env.block.chain_id),
+            });
+        }
+
+        let mut refreshed = osmosis::refresh_pool_info(deps.as_ref(), &pool_info)?;
+        validate_pool_info(&refreshed)?;
+        // A tick snapshot's liquidity isn't queried, so the pool is only as
+        // fresh as the snapshot an updater last pushed
+        if refreshed.cl_snapshot.is_none() {
+            refreshed.last_updated = Some(env.block.time.seconds());
+        }
+        POOL_REGISTRY.save(deps.storage, *pool_id, &refreshed)?;
+    }
+
+    let pool_ids: Vec<String> = pool_ids.iter().map(|id| id.to_string()).collect();
+    Ok(Response::new()
+        .add_attribute("method", "refresh_pools")
+        .add_attribute("pool_ids", pool_ids.join(",")))
+}
+
+fn add_pool_updater(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    // Only admin can manage pool updaters
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let updater = deps.api.addr_validate(&address)?;
+    POOL_UPDATERS.save(deps.storage, &updater, &Empty {})?;
+
+    Ok(Response::new()
+        .add_attribute("method", "add_pool_updater")
+        .add_attribute("updater", updater))
+}
+
+fn remove_pool_updater(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    // Only admin can manage pool updaters
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let updater = deps.api.addr_validate(&address)?;
+    POOL_UPDATERS.remove(deps.storage, &updater);
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_pool_updater")
+        .add_attribute("updater", updater))
+}
+
+/// Replace a pool's liquidity with the amounts pushed by the admin or a pool
+/// updater, such as an off-chain price feeder
+fn update_pool_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    pool_id: u64,
+    liquidity: Vec<Coin>,
+    cl_snapshot: Option<ClSnapshot>,
+    scaling_factors: Option<Vec<u64>>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin && !POOL_UPDATERS.has(deps.storage, &info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut pool_info = POOL_REGISTRY
+        .may_load(deps.storage, pool_id)?
+        .ok_or(ContractError::PoolNotFound { pool_id })?;
+
+    // Exactly one coin per pool denom
+    let covers_pool = liquidity.len() == pool_info.token_denoms.len()
+        && pool_info
+            .token_denoms
+            .iter()
+            .all(|denom| liquidity.iter().filter(|coin| &coin.denom == denom).count() == 1);
+    if !covers_pool {
+        return Err(ContractError::InvalidInput {
+            msg: format!("Liquidity must have one coin for each denom of pool {}", pool_id),
+        });
+    }
+
+    // Liquidity alone doesn't price concentrated pools with a snapshot, nor stableswap pools
+    let complete = match pool_info.pool_type {
+        PoolType::ConcentratedLiquidity => pool_info.cl_snapshot.is_none() || cl_snapshot.is_some(),
+        PoolType::StableSwap => scaling_factors.is_some(),
+        _ => true,
+    };
+    if let Some(snapshot) = cl_snapshot {
+        if pool_info.pool_type != PoolType::ConcentratedLiquidity {
+            return Err(ContractError::InvalidInput {
+                msg: format!("Pool {} is not a concentrated liquidity pool", pool_id),
+            });
+        }
+        pool_info.cl_snapshot = Some(snapshot);
+    }
+    if let Some(scaling_factors) = scaling_factors {
+        let params = pool_info.stable_swap.as_mut().ok_or_else(|| ContractError::InvalidInput {
+            msg: format!("Pool {} is not a stableswap pool", pool_id),
+        })?;
+        params.scaling_factors = scaling_factors;
+    }
+
+    pool_info.liquidity = liquidity;
+    validate_pool_info(&pool_info)?;
+    if complete {
+        pool_info.last_updated = Some(env.block.time.seconds());
+    }
+    POOL_REGISTRY.save(deps.storage, pool_id, &pool_info)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_pool_liquidity")
+        .add_attribute("pool_id", pool_id.to_string())
+        .add_attribute("complete", complete.to_string()))
+}
+
+fn update_max_pool_staleness(
+    deps: DepsMut,
+    info: MessageInfo,
+    max_pool_staleness: u64,
+) -> Result<Response, ContractError> {
+    // Only admin can update routing parameters
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut protocol_config = load_protocol_config(deps.storage)?;
+    protocol_config.routing.max_pool_staleness = max_pool_staleness;
+    save_protocol_config(deps.storage, &protocol_config)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "update_max_pool_staleness")
+        .add_attribute("max_pool_staleness", max_pool_staleness.to_string()))
+}
+
+/// Reject routes through pools whose data is older than `max_pool_staleness`
+fn check_pools_fresh(deps: Deps, env: &Env, routes: &[HopRoute]) -> Result<(), ContractError> {
+    let max_staleness = load_protocol_config(deps.storage)?.routing.max_pool_staleness;
+    for hop in routes {
+        let pool_info = POOL_REGISTRY
+            .may_load(deps.storage, hop.pool_id)?
+            .ok_or(ContractError::PoolNotFound { pool_id: hop.pool_id })?;
+        if !is_pool_fresh(&pool_info, env.block.time.seconds(), max_staleness) {
+            return Err(ContractError::StalePoolData { pool_id: hop.pool_id });
+        }
+    }
+    Ok(())
+}
+
+/// Check that a pool has at least two tokens, one weight per token if weighted,
+/// and the parameters its type needs
+fn validate_pool_info(pool_info: &PoolInfo) -> Result<(), ContractError> {
+    if pool_info.token_denoms.len() < 2 {
+        return Err(ContractError::InvalidPoolConfig {});
+    }
+
+    let weights = &pool_info.weights;
+    if !weights.is_empty()
+        && (weights.len() != pool_info.token_denoms.len() || weights.iter().any(|w| w.is_zero()))
+    {
+        return Err(ContractError::InvalidPoolConfig {});
+    }
+
+    if pool_info.pool_type == PoolType::StableSwap {
+        let params = pool_info.stable_swap.as_ref().ok_or(ContractError::InvalidPoolConfig {})?;
+        let factors = &params.scaling_factors;
+        if params.amplification == 0
+            || !(factors.is_empty() || factors.len() == pool_info.token_denoms.len())
+            || factors.contains(&0)
+        {
+            return Err(ContractError::InvalidPoolConfig {});
+        }
+    }
+    Ok(())
+}
+
+fn remove_pool(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+) -> Result<Response, ContractError> {
+    // Only admin can remove pools
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pool_info = POOL_REGISTRY
+        .may_load(deps.storage, pool_id)?
+        .ok_or(ContractError::PoolNotFound { pool_id })?;
+
+    POOL_REGISTRY.remove(deps.storage, pool_id);
+    unindex_pool_denoms(deps.storage, &pool_info);
+    unindex_pool_pairs(deps.storage, &pool_info)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "remove_pool")
+        .add_attribute("pool_id", pool_id.to_string()))
+}
+
+fn set_pool_active(
+    deps: DepsMut,
+    info: MessageInfo,
+    pool_id: u64,
+    active: bool,
+) -> Result<Response, ContractError> {
+    // Only admin can enable or disable pools
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let mut pool_info = POOL_REGISTRY
+        .may_load(deps.storage, pool_id)?
+        .ok_or(ContractError::PoolNotFound { pool_id })?;
+    pool_info.active = active;
+    POOL_REGISTRY.save(deps.storage, pool_id, &pool_info)?;
+
+    Ok(Response::new()
+        .add_attribute("method", "set_pool_active")
+        .add_attribute("pool_id", pool_id.to_string())
+        .add_attribute("active", active.to_string()))
+}
+
 fn execute_multi_hop_swap(
     deps: DepsMut,
     env: Env,
@@ -200,6 +472,7 @@ fn execute_multi_hop_swap(
     routes: Vec<HopRoute>,
     min_output: Uint128,
     timeout_timestamp: u64,
+    receiver: Option<String>,
 ) -> Result<Response, ContractError> {
     // Validate timeout
     if timeout_timestamp <= env.block.time.seconds() {
@@ -210,9 +483,10 @@ fn execute_multi_hop_swap(
     if routes.is_empty() {
         return Err(ContractError::NoRouteFound {});
     }
+    check_pools_fresh(deps.as_ref(), &env, &routes)?;
 
     // Estimate output
-    let amount_in = info.funds[0].amount; // Assume single token
+    let amount_in = route_amount_in(&info, &routes[0].token_in_denom)?;
     let (estimated_output, _) = estimate_multi_hop_swap(deps.as_ref(), routes.clone(), amount_in)?;
 
     // Check slippage
@@ -223,47 +497,278 @@ fn execute_multi_hop_swap(
         });
     }
 
-    // Build IBC transfer messages for multi-hop execution
-    let messages = build_multi_hop_messages(
+    let (messages, packets) = build_route_messages(
         deps.as_ref(),
         &env,
         &info,
-        routes.clone(),
+        &routes,
         amount_in,
         min_output,
         timeout_timestamp,
+        receiver.as_deref(),
     )?;
+    if !packets.is_empty() {
+        PENDING_PACKETS.save(deps.storage, &packets)?;
+    }
 
     Ok(Response::new()
-        .add_messages(messages)
+        .add_submessages(messages)
         .add_attribute("method", "execute_multi_hop_swap")
         .add_attribute("routes", format!("{:?}", routes))
         .add_attribute("estimated_output", estimated_output.to_string()))
 }
 
+#[allow(clippy::too_many_arguments)]
+fn execute_split_swap(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    routes: Vec<HopRoute>,
+    legs: Vec<SplitLeg>,
+    min_output: Uint128,
+    timeout_timestamp: u64,
+    receiver: Option<String>,
+) -> Result<Response, ContractError> {
+    // Validate timeout
+    if timeout_timestamp <= env.block.time.seconds() {
+        return Err(ContractError::Std(StdError::generic_err("Invalid timeout")));
+    }
+
+    validate_split_legs(&routes, &legs)?;
+    for leg in &legs {
+        check_pools_fresh(deps.as_ref(), &env, &leg.route)?;
+    }
+
+    // The legs only guarantee their own minimums, so together they must cover min_output
+    let legs_min_output: Uint128 = legs.iter().map(|leg| leg.min_output).sum();
+    if legs_min_output < min_output {
+        return Err(ContractError::SlippageExceeded {
+            expected: min_output.to_string(),
+            actual: legs_min_output.to_string(),
+        });
+    }
+
+    let amount_in = route_amount_in(&info, &legs[0].route[0].token_in_denom)?;
+    let percents: Vec<u8> = legs.iter().map(|leg| leg.percent).collect();
+    let amounts = split_amounts(amount_in, &percents);
+
+    let mut messages = vec![];
+    let mut packets = vec![];
+    let mut estimated_output = Uint128::zero();
+    for (leg, amount) in legs.iter().zip(amounts) {
+        let (leg_output, _) = estimate_multi_hop_swap(deps.as_ref(), leg.route.clone(), amount)?;
+        if leg_output < leg.min_output {
+            return Err(ContractError::SlippageExceeded {
+                expected: leg.min_output.to_string(),
+                actual: leg_output.to_string(),
+            });
+        }
+        estimated_output += leg_output;
+
+        let (leg_messages, leg_packets) = build_route_messages(
+            deps.as_ref(),
+            &env,
+            &info,
+            &leg.route,
+            amount,
+            leg.min_output,
+            timeout_timestamp,
+            receiver.as_deref(),
+        )?;
+        messages.extend(leg_messages);
+        packets.extend(leg_packets);
+    }
+    if !packets.is_empty() {
+        PENDING_PACKETS.save(deps.storage, &packets)?;
+    }
+
+    Ok(Response::new()
+        .add_submessages(messages)
+        .add_attribute("method", "execute_split_swap")
+        .add_attribute("legs", legs.len().to_string())
+        .add_attribute("estimated_output", estimated_output.to_string()))
+}
+
+/// Amount sent for a route starting with `denom`, which must be the only coin attached
+fn route_amount_in(info: &MessageInfo, denom: &str) -> Result<Uint128, ContractError> {
+    match info.funds.as_slice() {
+        [coin] if coin.denom == denom && !coin.amount.is_zero() => Ok(coin.amount),
+        _ => Err(ContractError::InvalidInput {
+            msg: format!("exactly one {} coin must be sent", denom),
+        }),
+    }
+}
+
+/// Check that split legs are well formed: non-empty, pool-disjoint routes
+/// between the same two denoms, with percentages adding up to 100.
+fn validate_split_legs(routes: &[HopRoute], legs: &[SplitLeg]) -> Result<(), ContractError> {
+    if !routes.is_empty() {
+        return Err(ContractError::InvalidInput {
+            msg: "routes must be empty when a split is given".to_string(),
+        });
+    }
+    if legs.is_empty() || legs.iter().any(|leg| leg.route.is_empty()) {
+        return Err(ContractError::NoRouteFound {});
+    }
+    if legs.iter().any(|leg| leg.percent == 0)
+        || legs.iter().map(|leg| leg.percent as u32).sum::<u32>() != 100
+    {
+        return Err(ContractError::InvalidInput {
+            msg: "split percentages must be positive and add up to 100".to_string(),
+        });
+    }
+
+    let first = &legs[0].route;
+    let (denom_in, denom_out) = (&first[0].token_in_denom, &first[first.len() - 1].token_out_denom);
+    let mut pool_ids = HashSet::new();
+    for leg in legs {
+        if &leg.route[0].token_in_denom != denom_in
+            || &leg.route[leg.route.len() - 1].token_out_denom != denom_out
+        {
+            return Err(ContractError::InvalidInput {
+                msg: "split legs must swap between the same denoms".to_string(),
+            });
+        }
+        for hop in &leg.route {
+            if !pool_ids.insert(hop.pool_id) {
+                return Err(ContractError::InvalidInput {
+                    msg: format!("pool {} is used by more than one split leg", hop.pool_id),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Build the IBC messages for one route, in the mode of its first hop's chain,
+/// along with the packets to track once their sequences are known
+#[allow(clippy::too_many_arguments)]
+fn build_route_messages(
+    deps: Deps,
+    env: &Env,
+    info: &MessageInfo,
+    routes: &[HopRoute],
+    amount_in: Uint128,
+    min_output: Uint128,
+    timeout_timestamp: u64,
+    receiver: Option<&str>,
+) -> Result<(Vec<SubMsg>, Vec<InFlightSwap>), ContractError> {
+    let first_chain = CHAIN_CONFIGS.load(deps.storage, &routes[0].chain_id)?;
+    let last_chain = CHAIN_CONFIGS.load(deps.storage, &routes[routes.len() - 1].chain_id)?;
+    
+    // The output is paid out on the last swap chain, so the receiver must be an address there
+    if let Some(receiver) = receiver {
+        if !receiver.starts_with(&format!("{}1", last_chain.chain_prefix)) {
+            return Err(ContractError::InvalidInput {
+                msg: format!("receiver must be a {} address", last_chain.chain_prefix),
+            });
+        }
+    }
+    
+    let routed: (Vec<SubMsg>, Vec<InFlightSwap>) = match first_chain.routing_mode {
+        // The reply gives the packet's sequence, under which its ack or timeout settles it
+        RoutingMode::FusionPacket => {
+            let (msg, packet) = build_multi_hop_message(
+                deps,
+                info,
+                routes,
+                amount_in,
+                min_output,
+                timeout_timestamp,
+                receiver.unwrap_or(info.sender.as_str()),
+            )?;
+            (vec![SubMsg::reply_on_success(msg, ibc::SEND_PACKET_REPLY_ID)], vec![packet])
+        }
+        RoutingMode::Ics20Pfm => {
+            let receiver = receiver.ok_or_else(|| ContractError::InvalidInput {
+                msg: format!("a {} receiver on {} is required", last_chain.chain_prefix, last_chain.chain_id),
+            })?;
+            let transfer = build_pfm_transfer(
+                deps,
+                env,
+                routes,
+                amount_in,
+                min_output,
+                timeout_timestamp,
+                &chain_receiver(&last_chain.chain_id, receiver),
+            )?;
+            // ibc-hooks reports the transfer's ack or timeout, the funds of a
+            // failed one coming back to this contract
+            let packet = InFlightSwap {
+                sender: info.sender.to_string(),
+                hop_index: 0,
+                channel_id: first_chain.ibc_channel.clone(),
+                denom: routes[0].token_in_denom.clone(),
+                amount: amount_in,
+                status: SwapStatus::InFlight,
+            };
+            (vec![SubMsg::reply_on_success(transfer, ibc::TRANSFER_REPLY_ID)], vec![packet])
+        }
+    };
+    Ok(routed)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        ibc::SWAP_REPLY_ID => ibc::swap_reply(deps, env, msg),
+        ibc::SEND_PACKET_REPLY_ID => ibc::send_packet_reply(deps, msg),
+        ibc::TRANSFER_REPLY_ID => ibc::transfer_reply(deps, msg),
+        id => Err(ContractError::Std(StdError::generic_err(format!("Unknown reply id: {}", id)))),
+    }
+}
+
+/// Outcomes of the ICS-20 transfers sent by multi-hop swaps, reported by ibc-hooks
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCAck {
+            channel,
+            sequence,
+            success,
+            ..
+        }) => ibc::ibc_lifecycle_complete(deps, channel, sequence, success),
+        SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCTimeout { channel, sequence }) => {
+            ibc::ibc_lifecycle_complete(deps, channel, sequence, false)
+        }
+    }
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetConfig {} => to_json_binary(&query_config(deps)?),
         QueryMsg::GetChainConfig { chain_id } => {
             to_json_binary(&query_chain_config(deps, chain_id)?)
         }
         QueryMsg::GetPoolInfo { pool_id } => to_json_binary(&query_pool_info(deps, pool_id)?),
+        QueryMsg::ListPools { chain_id, denom, start_after, limit } => {
+            to_json_binary(&query_list_pools(deps, chain_id, denom, start_after, limit)?)
+        }
+        QueryMsg::ListPoolUpdaters { start_after, limit } => {
+            to_json_binary(&query_list_pool_updaters(deps, start_after, limit)?)
+        }
         QueryMsg::FindBestRoute {
             start_denom,
             end_denom,
             amount_in,
             max_hops,
+            split,
         } => to_json_binary(&query_find_best_route(
             deps,
+            env,
             start_denom,
             end_denom,
             amount_in,
             max_hops,
+            split,
         )?),
         QueryMsg::EstimateMultiHopSwap { routes, amount_in } => {
             to_json_binary(&query_estimate_multi_hop(deps, routes, amount_in)?)
         }
+        QueryMsg::GetInFlightSwap { channel_id, sequence } => {
+            to_json_binary(&query_in_flight_swap(deps, channel_id, sequence)?)
+        }
     }
 }
 
@@ -292,14 +797,72 @@ fn query_pool_info(deps: Deps, pool_id: u64) -> StdResult<PoolInfoResponse> {
     Ok(PoolInfoResponse { pool_info })
 }
 
+fn query_list_pools(
+    deps: Deps,
+    chain_id: Option<String>,
+    denom: Option<String>,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ListPoolsResponse> {
+    let limit = limit.unwrap_or(10).min(100) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    // Walk the denom index when filtering by denom, otherwise the whole registry
+    let pool_ids: Box<dyn Iterator<Item = StdResult<u64>> + '_> = match &denom {
+        Some(denom) => Box::new(POOL_DENOMS.prefix(denom).keys(
+            deps.storage,
+            start,
+            None,
+            cosmwasm_std::Order::Ascending,
+        )),
+        None => Box::new(POOL_REGISTRY.keys(deps.storage, start, None, cosmwasm_std::Order::Ascending)),
+    };
+
+    let pools: StdResult<Vec<PoolInfo>> = pool_ids
+        .map(|pool_id| POOL_REGISTRY.load(deps.storage, pool_id?))
+        .filter(|item| match (item, &chain_id) {
+            (Ok(pool_info), Some(chain_id)) => &pool_info.chain_id == chain_id,
+            _ => true,
+        })
+        .take(limit)
+        .collect();
+
+    Ok(ListPoolsResponse { pools: pools? })
+}
+
+fn query_in_flight_swap(deps: Deps, channel_id: String, sequence: u64) -> StdResult<InFlightSwapResponse> {
+    let swap = IN_FLIGHT_SWAPS.load(deps.storage, (&channel_id, sequence))?;
+    Ok(InFlightSwapResponse { swap })
+}
+
+fn query_list_pool_updaters(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PoolUpdatersResponse> {
+    let limit = limit.unwrap_or(10).min(100) as usize;
+    let start_after = start_after.map(|addr| deps.api.addr_validate(&addr)).transpose()?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    let updaters: StdResult<Vec<String>> = POOL_UPDATERS
+        .keys(deps.storage, start, None, cosmwasm_std::Order::Ascending)
+        .take(limit)
+        .map(|addr| addr.map(String::from))
+        .collect();
+
+    Ok(PoolUpdatersResponse { updaters: updaters? })
+}
+
 fn query_find_best_route(
     deps: Deps,
+    env: Env,
     start_denom: String,
     end_denom: String,
     amount_in: Uint128,
     max_hops: Option<u32>,
+    split: bool,
 ) -> StdResult<RouteResponse> {
-    let routes = find_best_routes(deps, start_denom, end_denom, amount_in, max_hops)?;
+    let routes = find_best_routes(deps, env.block.time.seconds(), start_denom, end_denom, amount_in, max_hops)?;
 
     if routes.is_empty() {
         return Ok(RouteResponse {
@@ -307,6 +870,7 @@ fn query_find_best_route(
             estimated_output: Uint128::zero(),
             total_fees: Decimal::zero(),
             price_impact: Decimal::zero(),
+            split_plan: None,
         });
     }
 
@@ -316,11 +880,19 @@ fn query_find_best_route(
     // Calculate price impact
     let price_impact = calculate_price_impact(deps, &best_route.path, amount_in)?;
 
+    // The ranked routes share no pools, so they can be split across
+    let split_plan = if split {
+        Some(find_split_plan(deps, &routes, amount_in)?)
+    } else {
+        None
+    };
+
     Ok(RouteResponse {
         routes: routes.into_iter().map(|r| r.path).collect(),
         estimated_output: best_route.amount,
         total_fees: best_route.total_fee,
         price_impact,
+        split_plan,
     })
 }
 
@@ -364,149 +936,227 @@ fn query_estimate_multi_hop(
     })
 }
 
-fn build_multi_hop_messages(
+/// Build the packet to the first hop's chain. Its memo nests every remaining hop, so each
+/// router swaps and forwards the actual output to the next until the last pays `receiver`.
+fn build_multi_hop_message(
     deps: Deps,
-    _env: &Env,
     info: &MessageInfo,
-    routes: Vec<HopRoute>,
+    routes: &[HopRoute],
     amount_in: Uint128,
     min_output: Uint128,
     timeout_timestamp: u64,
-) -> StdResult<Vec<CosmosMsg>> {
-    let mut messages = vec![];
-    let mut current_amount = amount_in;
-    let mut current_denom = info.funds[0].denom.clone();
+    receiver: &str,
+) -> StdResult<(CosmosMsg, InFlightSwap)> {
+    let chain_config = CHAIN_CONFIGS.load(deps.storage, &routes[0].chain_id)?;
+    let min_outputs = hop_min_outputs(deps, routes, amount_in, min_output)?;
+    let memo = build_hop_memo(deps, routes, &min_outputs)?;
     
     // Calculate timeout for IBC transfers (buffer before the overall timeout)
     let ibc_timeout = IbcTimeout::with_timestamp(Timestamp::from_seconds(
         timeout_timestamp.saturating_sub(IBC_TIMEOUT_BUFFER)
     ));
     
-    for (i, hop) in routes.iter().enumerate() {
-        // Get chain config
-        let chain_config = CHAIN_CONFIGS.load(deps.storage, &hop.chain_id)?;
-        
-        // Build memo for the destination chain
-        let _memo = build_hop_memo(
-            deps,
-            hop,
-            i == routes.len() - 1, // is final hop
-            min_output,
-            &routes[i + 1..], // remaining hops
-        )?;
-        
-        // Build memo for the destination chain
-        let memo = build_hop_memo(
-            deps,
-            hop,
-            i == routes.len() - 1, // is final hop
-            min_output,
-            &routes[i + 1..], // remaining hops
-        )?;
-        
-        // Create IBC packet data with memo support
-        let packet_data = crate::msg::IbcPacketData {
-            sender: info.sender.to_string(),
-            receiver: if i == routes.len() - 1 {
-                // Final hop - send to actual receiver
-                info.sender.to_string()
-            } else {
-                // Intermediate hop - send to router contract on next chain
-                get_router_address(deps, &hop.chain_id)?
-            },
-            denom: current_denom.clone(),
-            amount: current_amount,
-            memo: Some(memo),
-        };
-        
-        // Use our custom IBC send packet message
-        let ibc_msg = IbcMsg::SendPacket {
-            channel_id: chain_config.ibc_channel.clone(),
-            data: to_json_binary(&packet_data)?,
-            timeout: ibc_timeout.clone(),
-        };
-        
-        messages.push(CosmosMsg::Ibc(ibc_msg));
-        
-        // Update current amount and denom for next hop
-        if i < routes.len() - 1 {
-            let pool_info = POOL_REGISTRY.load(deps.storage, hop.pool_id)?;
-            let (output_amount, _) = crate::routing::calculate_swap_output(
-                &pool_info,
-                &current_denom,
-                &hop.token_out_denom,
-                current_amount,
-            )?;
-            current_amount = output_amount;
-            current_denom = hop.token_out_denom.clone();
-        }
-    }
+    let packet_data = crate::msg::IbcPacketData {
+        sender: info.sender.to_string(),
+        receiver: receiver.to_string(),
+        denom: routes[0].token_in_denom.clone(),
+        amount: amount_in,
+        memo: Some(memo),
+    };
+    let ibc_msg = IbcMsg::SendPacket {
+        channel_id: chain_config.ibc_channel.clone(),
+        data: to_json_binary(&packet_data)?,
+        timeout: ibc_timeout,
+    };
     
-    Ok(messages)
+    let packet = InFlightSwap {
+        sender: info.sender.to_string(),
+        hop_index: 0,
+        channel_id: chain_config.ibc_channel,
+        denom: packet_data.denom,
+        amount: amount_in,
+        status: SwapStatus::InFlight,
+    };
+    Ok((CosmosMsg::Ibc(ibc_msg), packet))
 }
 
+/// Build the memo for the first of `routes`: its swap instruction, forwarding
+/// to the next hop's router with that hop's instruction nested, and so on
 fn build_hop_memo(
     deps: Deps,
-    hop: &HopRoute,
-    is_final: bool,
-    min_output: Uint128,
-    remaining_hops: &[HopRoute],
+    routes: &[HopRoute],
+    min_outputs: &[Uint128],
 ) -> StdResult<String> {
-    #[derive(serde::Serialize)]
-    struct HopMemo {
-        swap: SwapInstruction,
-        forward: Option<ForwardInstruction>,
-    }
+    let swap = build_swap_instruction(deps, routes, min_outputs)?;
     
-    #[derive(serde::Serialize)]
-    struct SwapInstruction {
-        pool_id: u64,
-        token_out_denom: String,
-        min_output: Option<String>,
-    }
+    serde_json::to_string(&swap)
+        .map_err(|e| StdError::generic_err(format!("Failed to serialize memo: {}", e)))
+}
+
+fn build_swap_instruction(
+    deps: Deps,
+    routes: &[HopRoute],
+    min_outputs: &[Uint128],
+) -> StdResult<SwapInstruction> {
+    let (hop, remaining_hops) = routes
+        .split_first()
+        .ok_or_else(|| StdError::generic_err("Swap routes cannot be empty"))?;
+    let min_output = *min_outputs
+        .first()
+        .ok_or_else(|| StdError::generic_err("Missing minimum output for hop"))?;
     
-    #[derive(serde::Serialize)]
-    struct ForwardInstruction {
-        port: String,
-        channel: String,
-        receiver: String,
-        timeout: u64,
-        retries: u8,
-        next: Option<Box<HopMemo>>,
-    }
+    let forward = match remaining_hops.first() {
+        Some(next_hop) => {
+            let next_chain_config = CHAIN_CONFIGS
+                .may_load(deps.storage, &next_hop.chain_id)?
+                .ok_or_else(|| StdError::generic_err("Next chain config not found"))?;
+            
+            Some(ForwardInstruction {
+                port: "transfer".to_string(),
+                channel: next_chain_config.ibc_channel,
+                receiver: get_router_address(deps, &next_hop.chain_id)?,
+                timeout: IBC_TIMEOUT_BUFFER,
+                retries: 0,
+                next: Some(Box::new(build_swap_instruction(
+                    deps,
+                    remaining_hops,
+                    &min_outputs[1..],
+                )?)),
+            })
+        }
+        None => None,
+    };
     
-    let swap = SwapInstruction {
+    Ok(SwapInstruction {
         pool_id: hop.pool_id,
         token_out_denom: hop.token_out_denom.clone(),
-        min_output: if is_final {
-            Some(min_output.to_string())
-        } else {
-            None
-        },
-    };
+        min_output: Some(min_output),
+        forward,
+    })
+}
+
+/// Build a single ICS-20 transfer to the first hop's chain whose memo carries the whole route,
+/// the last swap paying `final_receiver`, given as `chain_receiver` builds it. The memo also
+/// asks ibc-hooks to report the transfer's ack or timeout back to this contract.
+/// Each swap runs on the crosschain-swaps contract registered for its chain.
+/// `IbcMsg::Transfer` has no memo field before CosmWasm 2.0, so it is sent as `MsgTransfer`.
+fn build_pfm_transfer(
+    deps: Deps,
+    env: &Env,
+    routes: &[HopRoute],
+    amount_in: Uint128,
+    min_output: Uint128,
+    timeout_timestamp: u64,
+    final_receiver: &str,
+) -> StdResult<CosmosMsg> {
+    let mut chain_configs = Vec::with_capacity(routes.len());
+    for hop in routes {
+        let chain_config = CHAIN_CONFIGS.load(deps.storage, &hop.chain_id)?;
+        if chain_config.routing_mode != RoutingMode::Ics20Pfm {
+            return Err(StdError::generic_err(format!(
+                "Chain {} does not support ICS-20 routing",
+                hop.chain_id
+            )));
+        }
+        chain_configs.push(chain_config);
+    }
+    
+    let hop_timeout = format!("{}s", IBC_TIMEOUT_BUFFER);
+    let min_outputs = hop_min_outputs(deps, routes, amount_in, min_output)?;
     
-    let forward = if !is_final && !remaining_hops.is_empty() {
-        let next_hop = &remaining_hops[0];
-        let next_chain_config = CHAIN_CONFIGS
-            .may_load(deps.storage, &next_hop.chain_id)?
-            .ok_or_else(|| StdError::generic_err("Next chain config not found"))?;
+    // Nest the memos from the last hop backwards. Every swap but the last sends its
+    // output back to this chain, which forwards it to the next swap chain.
+    let mut memo: Option<Memo> = None;
+    for (i, hop) in routes.iter().enumerate().rev() {
+        let (receiver, next_memo) = match memo.take() {
+            None => (final_receiver.to_string(), None),
+            Some(next_swap) => {
+                let forward = Memo::Forward(ForwardMemo {
+                    receiver: get_crosschain_swaps_address(deps, &routes[i + 1].chain_id)?,
+                    port: "transfer".to_string(),
+                    channel: chain_configs[i + 1].ibc_channel.clone(),
+                    timeout: hop_timeout.clone(),
+                    retries: 0,
+                    next: Some(Box::new(next_swap)),
+                });
+                (
+                    chain_receiver(&env.block.chain_id, env.contract.address.as_str()),
+                    Some(Box::new(forward)),
+                )
+            }
+        };
         
-        Some(ForwardInstruction {
-            port: "transfer".to_string(),
-            channel: next_chain_config.ibc_channel,
-            receiver: get_router_address(deps, &next_hop.chain_id)?,
-            timeout: IBC_TIMEOUT_BUFFER,
-            retries: 0,
-            next: None, // Simplified for now to avoid recursion complexity
-        })
-    } else {
-        None
+        memo = Some(Memo::Wasm(WasmMemo {
+            contract: get_crosschain_swaps_address(deps, &hop.chain_id)?,
+            msg: SwapMsg::OsmosisSwap {
+                output_denom: hop.token_out_denom.clone(),
+                slippage: Slippage::MinOutputAmount(min_outputs[i]),
+                receiver,
+                on_failed_delivery: ON_FAILED_DELIVERY.to_string(),
+                next_memo,
+            },
+        }));
+    }
+    let memo = memo.ok_or_else(|| StdError::generic_err("Swap routes cannot be empty"))?;
+    
+    let transfer = MsgTransfer {
+        source_port: "transfer".to_string(),
+        source_channel: chain_configs[0].ibc_channel.clone(),
+        token: Some(osmosis_std::types::cosmos::base::v1beta1::Coin {
+            denom: routes[0].token_in_denom.clone(),
+            amount: amount_in.to_string(),
+        }),
+        sender: env.contract.address.to_string(),
+        receiver: get_crosschain_swaps_address(deps, &routes[0].chain_id)?,
+        timeout_height: None,
+        timeout_timestamp: Timestamp::from_seconds(
+            timeout_timestamp.saturating_sub(IBC_TIMEOUT_BUFFER)
+        ).nanos(),
+        memo: TransferMemo {
+            memo,
+            ibc_callback: env.contract.address.to_string(),
+        }
+        .to_json_string()?,
     };
     
-    let memo = HopMemo { swap, forward };
+    Ok(transfer.into())
+}
+
+/// Swap receiver as the crosschain swap contract takes it: the chain that must
+/// receive the funds, then the address there
+fn chain_receiver(chain_id: &str, address: &str) -> String {
+    format!("{}/{}", chain_id, address)
+}
+
+/// Minimum output of every hop. Intermediate hops get their estimated output
+/// reduced by the same tolerance the final `min_output` allows.
+fn hop_min_outputs(
+    deps: Deps,
+    routes: &[HopRoute],
+    amount_in: Uint128,
+    min_output: Uint128,
+) -> StdResult<Vec<Uint128>> {
+    let mut estimates = Vec::with_capacity(routes.len());
+    let mut current_amount = amount_in;
+    for hop in routes {
+        let pool_info = POOL_REGISTRY.load(deps.storage, hop.pool_id)?;
+        let (output_amount, _) = crate::routing::calculate_swap_output(
+            &pool_info,
+            &hop.token_in_denom,
+            &hop.token_out_denom,
+            current_amount,
+        )?;
+        estimates.push(output_amount);
+        current_amount = output_amount;
+    }
+    
+    let tolerance = Decimal::from_ratio(min_output, current_amount.max(Uint128::one())).min(Decimal::one());
+    let mut min_outputs: Vec<Uint128> = estimates.iter().map(|estimate| *estimate * tolerance).collect();
+    if let Some(last) = min_outputs.last_mut() {
+        *last = min_output;
+    }
     
-    serde_json::to_string(&memo)
-        .map_err(|e| StdError::generic_err(format!("Failed to serialize memo: {}", e)))
+    Ok(min_outputs)
 }
 
 fn get_router_address(deps: Deps, chain_id: &str) -> StdResult<String> {
@@ -531,6 +1181,12 @@ fn get_router_address(deps: Deps, chain_id: &str) -> StdResult<String> {
         .map_err(|_| StdError::generic_err(format!("No router registered for chain: {}", chain_id)))
 }
 
+fn get_crosschain_swaps_address(deps: Deps, chain_id: &str) -> StdResult<String> {
+    CROSSCHAIN_SWAPS_REGISTRY.load(deps.storage, chain_id).map_err(|_| {
+        StdError::generic_err(format!("No crosschain-swaps contract registered for chain: {}", chain_id))
+    })
+}
+
 fn register_router(
     deps: DepsMut,
     info: MessageInfo,
@@ -578,6 +1234,32 @@ fn remove_router(
         .add_attribute("chain_id", chain_id))
 }
 
+fn register_crosschain_swaps(
+    deps: DepsMut,
+    info: MessageInfo,
+    chain_id: String,
+    contract_address: String,
+) -> Result<Response, ContractError> {
+    // Only admin can register crosschain-swaps contracts
+    let config = CONFIG.load(deps.storage)?;
+    if info.sender != config.admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if contract_address.is_empty() {
+        return Err(ContractError::InvalidInput {
+            msg: "Crosschain-swaps address cannot be empty".to_string()
+        });
+    }
+
+    CROSSCHAIN_SWAPS_REGISTRY.save(deps.storage, &chain_id, &contract_address)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "register_crosschain_swaps")
+        .add_attribute("chain_id", chain_id)
+        .add_attribute("contract_address", contract_address))
+}
+
 fn update_registry_contract(
     deps: DepsMut,
     info: MessageInfo,
@@ -610,7 +1292,7 @@ mod tests {
     use super::*;
     use cosmwasm_std::testing::{mock_dependencies, mock_env, mock_info};
     use cosmwasm_std::{Decimal, Uint128, Coin, CosmosMsg, IbcMsg};
-    use crate::msg::PoolType;
+    use crate::msg::StableSwapParams;
 
     #[test]
     fn test_instantiate() {
@@ -626,6 +1308,7 @@ mod tests {
                     chain_prefix: "osmo".to_string(),
                     ibc_channel: "channel-0".to_string(),
                     native_denom: "uosmo".to_string(),
+                    routing_mode: RoutingMode::FusionPacket,
                 },
             ],
             registry_contract: None,
@@ -669,6 +1352,11 @@ mod tests {
             ],
             swap_fee: Decimal::permille(3),
             exit_fee: Decimal::zero(),
+            active: true,
+            weights: vec![],
+            stable_swap: None,
+            cl_snapshot: None,
+            last_updated: None,
         };
 
         let msg = ExecuteMsg::RegisterPool { pool_info };
@@ -691,12 +1379,14 @@ mod tests {
                     chain_prefix: "osmo".to_string(),
                     ibc_channel: "channel-0".to_string(),
                     native_denom: "uosmo".to_string(),
+                    routing_mode: RoutingMode::FusionPacket,
                 },
                 ChainConfig {
                     chain_id: "juno-1".to_string(),
                     chain_prefix: "juno".to_string(),
                     ibc_channel: "channel-1".to_string(),
                     native_denom: "ujuno".to_string(),
+                    routing_mode: RoutingMode::FusionPacket,
                 },
             ],
             registry_contract: None,
@@ -735,6 +1425,11 @@ mod tests {
             ],
             swap_fee: Decimal::permille(3),
             exit_fee: Decimal::zero(),
+            active: true,
+            weights: vec![],
+            stable_swap: None,
+            cl_snapshot: None,
+            last_updated: None,
         };
         execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::RegisterPool { pool_info: pool1 }).unwrap();
 
@@ -753,6 +1448,8 @@ mod tests {
             routes,
             min_output: Uint128::new(100),
             timeout_timestamp: env.block.time.seconds() + 3600,
+            split: None,
+            receiver: None,
         };
 
         let res = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap();
@@ -769,6 +1466,951 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_pfm_multi_hop_execution() {
+        use crate::test_helpers::{balancer_pool, register_pools, setup_router};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let chains = [("osmosis-1", "osmo", "channel-0"), ("juno-1", "juno", "channel-1")];
+        setup_router(deps.as_mut(), &env, &chains, RoutingMode::Ics20Pfm);
+        register_pools(
+            deps.as_mut(),
+            &env,
+            [
+                balancer_pool(1, "osmosis-1", ["uatom", "uosmo"], 1_000_000_000),
+                balancer_pool(2, "juno-1", ["uosmo", "ujuno"], 1_000_000_000),
+            ],
+        );
+
+        let routes = vec![
+            HopRoute {
+                chain_id: "osmosis-1".to_string(),
+                pool_id: 1,
+                token_in_denom: "uatom".to_string(),
+                token_out_denom: "uosmo".to_string(),
+            },
+            HopRoute {
+                chain_id: "juno-1".to_string(),
+                pool_id: 2,
+                token_in_denom: "uosmo".to_string(),
+                token_out_denom: "ujuno".to_string(),
+            },
+        ];
+        let swap = |receiver: Option<&str>| ExecuteMsg::ExecuteMultiHopSwap {
+            routes: routes.clone(),
+            min_output: Uint128::new(90_000),
+            timeout_timestamp: env.block.time.seconds() + 3600,
+            split: None,
+            receiver: receiver.map(|r| r.to_string()),
+        };
+        let user_info = mock_info("user", &[Coin::new(100_000, "uatom")]);
+
+        // The output is delivered on the last swap chain, to an address of that chain
+        for receiver in [None, Some("osmo1user")] {
+            let err = execute(deps.as_mut(), env.clone(), user_info.clone(), swap(receiver)).unwrap_err();
+            assert!(matches!(err, ContractError::InvalidInput { .. }));
+        }
+
+        // Exactly one coin of the route's input denom must be sent
+        for funds in [
+            vec![],
+            vec![Coin::new(100_000, "uosmo")],
+            vec![Coin::new(100_000, "uatom"), Coin::new(100_000, "uosmo")],
+        ] {
+            let err = execute(deps.as_mut(), env.clone(), mock_info("user", &funds), swap(Some("juno1user"))).unwrap_err();
+            assert!(matches!(err, ContractError::InvalidInput { .. }));
+        }
+
+        let res = execute(deps.as_mut(), env.clone(), user_info.clone(), swap(Some("juno1user"))).unwrap();
+
+        // The whole route is a single ICS-20 transfer to the first swap chain
+        assert_eq!(res.messages.len(), 1);
+        let transfer = match &res.messages[0].msg {
+            CosmosMsg::Stargate { type_url, value } => {
+                assert_eq!(type_url, MsgTransfer::TYPE_URL);
+                MsgTransfer::try_from(value.clone()).unwrap()
+            }
+            _ => panic!("Expected MsgTransfer"),
+        };
+        assert_eq!(transfer.source_channel, "channel-0");
+        assert_eq!(transfer.receiver, "osmo1crosschainswaps");
+        assert_eq!(transfer.token.unwrap().denom, "uatom");
+        assert_eq!(res.messages[0].id, ibc::TRANSFER_REPLY_ID);
+
+        // Swap on osmosis-1, come back and get forwarded to juno-1, swap there
+        let TransferMemo { memo, ibc_callback } = serde_json::from_str(&transfer.memo).unwrap();
+        assert_eq!(ibc_callback, env.contract.address.to_string());
+        let Memo::Wasm(WasmMemo { contract, msg: SwapMsg::OsmosisSwap { output_denom, slippage, receiver, next_memo, .. } }) = memo else {
+            panic!("Expected wasm memo");
+        };
+        assert_eq!(contract, "osmo1crosschainswaps");
+        assert_eq!(output_denom, "uosmo");
+        assert_eq!(receiver, format!("{}/{}", env.block.chain_id, env.contract.address));
+        let Slippage::MinOutputAmount(first_min) = slippage;
+        assert!(first_min > Uint128::new(90_000) && first_min < Uint128::new(100_000));
+
+        let Some(Memo::Forward(forward)) = next_memo.map(|m| *m) else {
+            panic!("Expected forward memo");
+        };
+        assert_eq!(forward.channel, "channel-1");
+        assert_eq!(forward.receiver, "juno1crosschainswaps");
+
+        let Some(Memo::Wasm(WasmMemo { contract, msg: SwapMsg::OsmosisSwap { output_denom, slippage, receiver, next_memo, .. } })) = forward.next.map(|m| *m) else {
+            panic!("Expected wasm memo");
+        };
+        assert_eq!(contract, "juno1crosschainswaps");
+        assert_eq!(output_denom, "ujuno");
+        assert_eq!(receiver, "juno-1/juno1user");
+        assert_eq!(slippage, Slippage::MinOutputAmount(Uint128::new(90_000)));
+        assert!(next_memo.is_none());
+
+        // Every swap chain needs its crosschain-swaps contract, which only the admin registers
+        crate::state::CROSSCHAIN_SWAPS_REGISTRY.remove(deps.as_mut().storage, "juno-1");
+        let err = execute(deps.as_mut(), env.clone(), user_info.clone(), swap(Some("juno1user"))).unwrap_err();
+        assert!(matches!(err, ContractError::Std(_)));
+
+        let msg = ExecuteMsg::RegisterCrosschainSwaps {
+            chain_id: "juno-1".to_string(),
+            contract_address: "juno1crosschainswaps".to_string(),
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(deps.as_mut(), env.clone(), mock_info("admin", &[]), msg).unwrap();
+        execute(deps.as_mut(), env.clone(), user_info, swap(Some("juno1user"))).unwrap();
+    }
+
+    #[test]
+    fn test_build_hop_memo_nests_every_hop() {
+        use crate::test_helpers::{balancer_pool, register_pools, setup_router};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let chains = [
+            ("osmosis-1", "osmo", "channel-0"),
+            ("juno-1", "juno", "channel-1"),
+            ("stargaze-1", "stars", "channel-2"),
+            ("neutron-1", "neutron", "channel-3"),
+        ];
+        setup_router(deps.as_mut(), &env, &chains, RoutingMode::FusionPacket);
+
+        let denoms = ["uatom", "uosmo", "ujuno", "ustars", "untrn"];
+        let routes: Vec<HopRoute> = chains
+            .iter()
+            .enumerate()
+            .map(|(i, (chain_id, _, _))| HopRoute {
+                chain_id: chain_id.to_string(),
+                pool_id: i as u64 + 1,
+                token_in_denom: denoms[i].to_string(),
+                token_out_denom: denoms[i + 1].to_string(),
+            })
+            .collect();
+        let min_outputs: Vec<Uint128> = [400u128, 300, 200, 100].into_iter().map(Uint128::new).collect();
+
+        let memo = build_hop_memo(deps.as_ref(), &routes, &min_outputs).unwrap();
+        let mut instruction: SwapInstruction = serde_json::from_str(&memo).unwrap();
+
+        // Every hop survives the round trip, each forwarding to the next hop's router
+        for i in 0..routes.len() {
+            assert_eq!(instruction.pool_id, routes[i].pool_id);
+            assert_eq!(instruction.token_out_denom, routes[i].token_out_denom);
+            assert_eq!(instruction.min_output, Some(min_outputs[i]));
+
+            match instruction.forward {
+                Some(forward) => {
+                    let (_, prefix, channel) = chains[i + 1];
+                    assert_eq!(forward.channel, channel);
+                    assert_eq!(forward.receiver, format!("{}1router", prefix));
+                    instruction = *forward.next.expect("Expected nested instruction");
+                }
+                None => {
+                    assert_eq!(i, routes.len() - 1);
+                    break;
+                }
+            }
+        }
+
+        // The route goes out as one packet to the first chain, carrying the whole memo
+        register_pools(
+            deps.as_mut(),
+            &env,
+            routes
+                .iter()
+                .map(|hop| {
+                    balancer_pool(hop.pool_id, &hop.chain_id, [hop.token_in_denom.as_str(), &hop.token_out_denom], 1_000_000)
+                }),
+        );
+        let msg = ExecuteMsg::ExecuteMultiHopSwap {
+            routes: routes.clone(),
+            min_output: Uint128::new(100),
+            timeout_timestamp: env.block.time.seconds() + 3600,
+            split: None,
+            receiver: Some("neutron1user".to_string()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user", &[Coin::new(1_000, "uatom")]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        let CosmosMsg::Ibc(IbcMsg::SendPacket { channel_id, data, .. }) = &res.messages[0].msg else {
+            panic!("Expected IBC SendPacket message");
+        };
+        assert_eq!(channel_id, "channel-0");
+        let packet: crate::msg::IbcPacketData = cosmwasm_std::from_json(data).unwrap();
+        assert_eq!(packet.receiver, "neutron1user");
+        let instruction: SwapInstruction = serde_json::from_str(&packet.memo.unwrap()).unwrap();
+        assert_eq!(instruction.pool_id, 1);
+        assert!(instruction.forward.is_some());
+    }
+
+    #[test]
+    fn test_routing_uses_denom_index() {
+        use crate::test_helpers::{balancer_pool, register_pools, setup_router};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        setup_router(deps.as_mut(), &env, &[], RoutingMode::FusionPacket);
+
+        // Pool ids far outside the old discovery range are found
+        let pool = |pool_id: u64, denoms: [&str; 2]| balancer_pool(pool_id, "osmosis-1", denoms, 1_000_000);
+        register_pools(deps.as_mut(), &env, [pool(5_000, ["uatom", "uosmo"]), pool(1_000_000, ["uosmo", "ujuno"])]);
+
+        let find_route = |deps: Deps, end_denom: &str| -> RouteResponse {
+            let msg = QueryMsg::FindBestRoute {
+                start_denom: "uatom".to_string(),
+                end_denom: end_denom.to_string(),
+                amount_in: Uint128::new(1_000),
+                max_hops: None,
+                split: false,
+            };
+            cosmwasm_std::from_json(query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+
+        let res = find_route(deps.as_ref(), "ujuno");
+        assert_eq!(res.routes.len(), 1);
+        let pool_ids: Vec<u64> = res.routes[0].iter().map(|hop| hop.pool_id).collect();
+        assert_eq!(pool_ids, vec![5_000, 1_000_000]);
+
+        // Updating a pool's denoms moves it in the index
+        let msg = ExecuteMsg::UpdatePoolInfo {
+            pool_id: 1_000_000,
+            pool_info: pool(1_000_000, ["uosmo", "ustars"]),
+        };
+        execute(deps.as_mut(), env, mock_info("admin", &[]), msg).unwrap();
+
+        assert!(find_route(deps.as_ref(), "ujuno").routes.is_empty());
+        assert_eq!(find_route(deps.as_ref(), "ustars").routes.len(), 1);
+    }
+
+    #[test]
+    fn test_best_output_routing() {
+        use crate::test_helpers::{balancer_pool, register_pools, setup_router};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        setup_router(deps.as_mut(), &env, &[], RoutingMode::FusionPacket);
+
+        // The shallow uatom/uosmo pool reaches uosmo first, but the deep route
+        // through uusdc gives more output
+        register_pools(
+            deps.as_mut(),
+            &env,
+            [
+                balancer_pool(1, "osmosis-1", ["uatom", "uosmo"], 10_000),
+                balancer_pool(2, "osmosis-1", ["uatom", "uusdc"], 1_000_000),
+                balancer_pool(3, "osmosis-1", ["uusdc", "uosmo"], 1_000_000),
+                balancer_pool(4, "osmosis-1", ["uosmo", "ujuno"], 1_000_000),
+            ],
+        );
+
+        let find_route = |deps: Deps, max_hops: Option<u32>| -> RouteResponse {
+            let msg = QueryMsg::FindBestRoute {
+                start_denom: "uatom".to_string(),
+                end_denom: "ujuno".to_string(),
+                amount_in: Uint128::new(1_000),
+                max_hops,
+                split: false,
+            };
+            cosmwasm_std::from_json(query(deps, mock_env(), msg).unwrap()).unwrap()
+        };
+        let pool_ids = |route: &Vec<HopRoute>| route.iter().map(|hop| hop.pool_id).collect::<Vec<_>>();
+
+        // [1, 4] shares pool 4 with the better route and is dropped
+        let res = find_route(deps.as_ref(), None);
+        assert_eq!(res.routes.len(), 1);
+        assert_eq!(pool_ids(&res.routes[0]), vec![2, 3, 4]);
+        let best_output = res.estimated_output;
+
+        let res = find_route(deps.as_ref(), Some(2));
+        assert_eq!(res.routes.len(), 1);
+        assert_eq!(pool_ids(&res.routes[0]), vec![1, 4]);
+        assert!(res.estimated_output < best_output);
+
+        // The exploration budget stops the search before any route completes
+        let mut protocol_config = ProtocolConfig::default();
+        protocol_config.routing.max_routes_to_explore = 2;
+        crate::state::save_protocol_config(deps.as_mut().storage, &protocol_config).unwrap();
+        assert!(find_route(deps.as_ref(), None).routes.is_empty());
+    }
+
+    #[test]
+    fn test_split_route_plan() {
+        use crate::test_helpers::{balancer_pool, register_pools, setup_router};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        setup_router(deps.as_mut(), &env, &[("osmosis-1", "osmo", "channel-0")], RoutingMode::FusionPacket);
+
+        // Two equally shallow pools for the same pair
+        register_pools(
+            deps.as_mut(),
+            &env,
+            [1, 2].map(|pool_id| balancer_pool(pool_id, "osmosis-1", ["uatom", "ujuno"], 100_000)),
+        );
+
+        let msg = QueryMsg::FindBestRoute {
+            start_denom: "uatom".to_string(),
+            end_denom: "ujuno".to_string(),
+            amount_in: Uint128::new(100_000),
+            max_hops: None,
+            split: true,
+        };
+        let res: RouteResponse = cosmwasm_std::from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        let plan = res.split_plan.unwrap();
+        assert_eq!(plan.legs.iter().map(|leg| leg.percent).collect::<Vec<_>>(), vec![50, 50]);
+        assert_eq!(plan.legs[0].amount_in + plan.legs[1].amount_in, Uint128::new(100_000));
+        assert!(plan.estimated_output > res.estimated_output);
+
+        let legs: Vec<SplitLeg> = plan
+            .legs
+            .iter()
+            .map(|leg| SplitLeg {
+                route: leg.route.clone(),
+                percent: leg.percent,
+                min_output: leg.estimated_output * Decimal::percent(99),
+            })
+            .collect();
+        let timeout_timestamp = env.block.time.seconds() + 3600;
+        let swap = |legs: Vec<SplitLeg>, min_output: Uint128| ExecuteMsg::ExecuteMultiHopSwap {
+            routes: vec![],
+            min_output,
+            timeout_timestamp,
+            split: Some(legs),
+            receiver: None,
+        };
+        let user_info = mock_info("user", &[Coin::new(100_000, "uatom")]);
+
+        // Every leg is sent on its own
+        let res = execute(deps.as_mut(), env.clone(), user_info.clone(), swap(legs.clone(), Uint128::new(60_000))).unwrap();
+        assert_eq!(res.messages.len(), 2);
+
+        // The legs' minimums must cover the aggregate minimum
+        let err = execute(deps.as_mut(), env.clone(), user_info.clone(), swap(legs.clone(), plan.estimated_output)).unwrap_err();
+        assert!(matches!(err, ContractError::SlippageExceeded { .. }));
+
+        // Each leg is checked against its own minimum
+        let mut greedy = legs.clone();
+        greedy[1].min_output = plan.legs[1].estimated_output + Uint128::one();
+        let err = execute(deps.as_mut(), env.clone(), user_info.clone(), swap(greedy, Uint128::new(60_000))).unwrap_err();
+        assert!(matches!(err, ContractError::SlippageExceeded { .. }));
+
+        let mut uneven = legs.clone();
+        uneven[0].percent = 60;
+        let err = execute(deps.as_mut(), env.clone(), user_info.clone(), swap(uneven, Uint128::new(60_000))).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidInput { .. }));
+
+        let mut overlapping = legs;
+        overlapping[1].route = overlapping[0].route.clone();
+        let err = execute(deps.as_mut(), env, user_info, swap(overlapping, Uint128::new(60_000))).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidInput { .. }));
+    }
+
+    #[test]
+    fn test_pool_lifecycle() {
+        use crate::state::POOL_PAIRS;
+        use crate::test_helpers::{balancer_pool, register_pools, setup_router};
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+        let info = mock_info("admin", &[]);
+
+        setup_router(deps.as_mut(), &env, &[], RoutingMode::FusionPacket);
+
+        let pool = |pool_id: u64, chain_id: &str, denoms: [&str; 2]| balancer_pool(pool_id, chain_id, denoms, 1_000_000);
+        register_pools(
+            deps.as_mut(),
+            &env,
+            [
+                pool(1, "osmosis-1", ["uatom", "uosmo"]),
+                pool(2, "juno-1", ["uosmo", "ujuno"]),
+                pool(3, "osmosis-1", ["uatom", "ujuno"]),
+            ],
+        );
+
+        let list = |deps: Deps, chain_id: Option<&str>, denom: Option<&str>, start_after: Option<u64>, limit: Option<u32>| -> Vec<u64> {
+            let msg = QueryMsg::ListPools {
+                chain_id: chain_id.map(String::from),
+                denom: denom.map(String::from),
+                start_after,
+                limit,
+            };
+            let res: ListPoolsResponse = cosmwasm_std::from_json(query(deps, mock_env(), msg).unwrap()).unwrap();
+            res.pools.into_iter().map(|p| p.pool_id).collect()
+        };
+        assert_eq!(list(deps.as_ref(), None, None, None, None), vec![1, 2, 3]);
+        assert_eq!(list(deps.as_ref(), None, None, Some(1), Some(1)), vec![2]);
+        assert_eq!(list(deps.as_ref(), Some("osmosis-1"), None, None, None), vec![1, 3]);
+        assert_eq!(list(deps.as_ref(), None, Some("ujuno"), None, None), vec![2, 3]);
+        assert_eq!(list(deps.as_ref(), Some("osmosis-1"), Some("ujuno"), None, None), vec![3]);
+
+        // Only the admin manages pools
+        let msg = ExecuteMsg::SetPoolActive { pool_id: 3, active: false };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+
+        // A deactivated pool is skipped by routing and rejected by estimation
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = QueryMsg::FindBestRoute {
+            start_denom: "uatom".to_string(),
+            end_denom: "ujuno".to_string(),
+            amount_in: Uint128::new(1_000),
+            max_hops: None,
+            split: false,
+        };
+        let res: RouteResponse = cosmwasm_std::from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.routes.len(), 1);
+        assert_eq!(res.routes[0].iter().map(|hop| hop.pool_id).collect::<Vec<_>>(), vec![1, 2]);
+
+        let msg = QueryMsg::EstimateMultiHopSwap {
+            routes: vec![HopRoute {
+                chain_id: "osmosis-1".to_string(),
+                pool_id: 3,
+                token_in_denom: "uatom".to_string(),
+                token_out_denom: "ujuno".to_string(),
+            }],
+            amount_in: Uint128::new(1_000),
+        };
+        assert!(query(deps.as_ref(), env.clone(), msg).is_err());
+
+        // Changing a pool's denoms moves it in the pair index
+        let msg = ExecuteMsg::UpdatePoolInfo {
+            pool_id: 2,
+            pool_info: pool(2, "juno-1", ["uosmo", "ustars"]),
+        };
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        assert!(POOL_PAIRS.may_load(&deps.storage, ("ujuno", "uosmo")).unwrap().is_none());
+        assert_eq!(POOL_PAIRS.load(&deps.storage, ("ustars", "uosmo")).unwrap(), vec![2]);
+
+        // Removing a pool drops it from every index
+        execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::RemovePool { pool_id: 1 }).unwrap();
+        assert!(query(deps.as_ref(), env.clone(), QueryMsg::GetPoolInfo { pool_id: 1 }).is_err());
+        assert!(POOL_PAIRS.may_load(&deps.storage, ("uatom", "uosmo")).unwrap().is_none());
+        assert_eq!(list(deps.as_ref(), None, Some("uatom"), None, None), vec![3]);
+
+        // StableSwap pools need their curve parameters
+        let mut stable = pool(4, "osmosis-1", ["uusdc", "uusdt"]);
+        stable.pool_type = PoolType::StableSwap;
+        let msg = ExecuteMsg::RegisterPool { pool_info: stable.clone() };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPoolConfig {}));
+
+        stable.stable_swap = Some(StableSwapParams { amplification: 100, scaling_factors: vec![1] });
+        let msg = ExecuteMsg::RegisterPool { pool_info: stable.clone() };
+        let err = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidPoolConfig {}));
+
+        stable.stable_swap = Some(StableSwapParams { amplification: 100, scaling_factors: vec![1, 1] });
+        execute(deps.as_mut(), env, info, ExecuteMsg::RegisterPool { pool_info: stable }).unwrap();
+    }
+
+    #[test]
+    fn test_refresh_pools() {
+        use crate::msg::{ClSnapshot, TickLiquidity};
+        use crate::test_helpers::{
+            balancer_pool, mock_dependencies_with_osmosis, register_pools, setup_router,
+        };
+        use cosmwasm_std::{Decimal256, SignedDecimal256};
+        use std::str::FromStr;
+
+        // Pool 3 is at tick 4,000,000 with a square root price of 1.5 and 2500 liquidity
+        let mut deps = mock_dependencies_with_osmosis(|querier| {
+            querier
+                .with_balancer_pool(1, &[("uosmo", 3_000_000, 20), ("uatom", 1_500_000, 80)], "0.002000000000000000")
+                .with_stableswap_pool(2, &[("uusdt", 5_000_000), ("uusdc", 4_000_000)], &[2, 1], "0.000500000000000000")
+                .with_concentrated_pool(
+                    3,
+                    &[("uatom", 700_000), ("uosmo", 900_000)],
+                    "0.001000000000000000",
+                    (4_000_000, "1500000000000000000000000000000000000", "2500000000000000000000"),
+                )
+        });
+        let mut env = mock_env();
+        setup_router(deps.as_mut(), &env, &[], RoutingMode::FusionPacket);
+
+        let chain_id = env.block.chain_id.clone();
+        let mut stable = balancer_pool(2, &chain_id, ["uusdc", "uusdt"], 1_000_000);
+        stable.pool_type = PoolType::StableSwap;
+        stable.stable_swap = Some(StableSwapParams { amplification: 100, scaling_factors: vec![] });
+        let ticks = vec![
+            TickLiquidity { tick_index: -9_000_000, liquidity_net: SignedDecimal256::from_str("1000000").unwrap() },
+            TickLiquidity { tick_index: 9_000_000, liquidity_net: SignedDecimal256::from_str("-1000000").unwrap() },
+        ];
+        let mut concentrated = balancer_pool(3, &chain_id, ["uatom", "uosmo"], 1_000_000);
+        concentrated.pool_type = PoolType::ConcentratedLiquidity;
+        concentrated.cl_snapshot = Some(ClSnapshot {
+            current_sqrt_price: Decimal256::one(),
+            current_tick: 0,
+            current_liquidity: Decimal256::from_ratio(1_000_000u128, 1u8),
+            ticks: ticks.clone(),
+        });
+        register_pools(
+            deps.as_mut(),
+            &env,
+            [
+                balancer_pool(1, &chain_id, ["uatom", "uosmo"], 1_000_000),
+                stable,
+                concentrated,
+                balancer_pool(4, "osmosis-1", ["uatom", "uosmo"], 1_000_000),
+            ],
+        );
+        let registered_at = env.block.time.seconds();
+
+        // Anyone may refresh, since the data comes from the chain
+        env.block.time = env.block.time.plus_seconds(60);
+        let msg = ExecuteMsg::RefreshPools { ids: vec![1, 2, 3] };
+        execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap();
+
+        let load = |deps: Deps, pool_id: u64| -> PoolInfo {
+            let msg = QueryMsg::GetPoolInfo { pool_id };
+            let res: PoolInfoResponse = cosmwasm_std::from_json(query(deps, mock_env(), msg).unwrap()).unwrap();
+            res.pool_info
+        };
+
+        // Weights follow the registered denom order
+        let balancer = load(deps.as_ref(), 1);
+        assert_eq!(balancer.swap_fee, Decimal::permille(2));
+        assert_eq!(balancer.weights, vec![Uint128::new(80), Uint128::new(20)]);
+        assert!(balancer.liquidity.contains(&Coin::new(1_500_000, "uatom")));
+        assert!(balancer.liquidity.contains(&Coin::new(3_000_000, "uosmo")));
+        assert_eq!(balancer.last_updated, Some(env.block.time.seconds()));
+
+        // The amplification is kept; scaling factors are reordered to the denoms
+        let stable = load(deps.as_ref(), 2);
+        assert_eq!(stable.swap_fee, Decimal::from_ratio(5u128, 10_000u128));
+        assert_eq!(
+            stable.stable_swap,
+            Some(StableSwapParams { amplification: 100, scaling_factors: vec![1, 2] })
+        );
+
+        // The snapshot's price, tick and active liquidity follow the pool; its ticks are
+        // kept, so the pool stays as stale as they are
+        let concentrated = load(deps.as_ref(), 3);
+        assert_eq!(concentrated.pool_type, PoolType::ConcentratedLiquidity);
+        assert_eq!(concentrated.swap_fee, Decimal::permille(1));
+        assert!(concentrated.liquidity.contains(&Coin::new(900_000, "uosmo")));
+        assert_eq!(
+            concentrated.cl_snapshot,
+            Some(ClSnapshot {
+                current_sqrt_price: Decimal256::from_str("1.5").unwrap(),
+                current_tick: 4_000_000,
+                current_liquidity: Decimal256::from_ratio(2_500u128, 1u8),
+                ticks,
+            })
+        );
+        assert_eq!(concentrated.last_updated, Some(registered_at));
+
+        // Pools on other chains can't be queried from here
+        let msg = ExecuteMsg::RefreshPool { pool_id: 4 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidInput { .. }));
+        assert_eq!(load(deps.as_ref(), 4).last_updated, Some(registered_at));
+
+        let msg = ExecuteMsg::RefreshPool { pool_id: 99 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("anyone", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::PoolNotFound { pool_id: 99 }));
+
+        let msg = ExecuteMsg::RefreshPools { ids: (1..=31).collect() };
+        let err = execute(deps.as_mut(), env, mock_info("anyone", &[]), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidInput { .. }));
+    }
+
+    #[test]
+    fn test_pool_updaters_and_staleness() {
+        use crate::msg::ClSnapshot;
+        use crate::test_helpers::{balancer_pool, register_pools, setup_router};
+        use cosmwasm_std::Decimal256;
+
+        let mut deps = mock_dependencies();
+        let mut env = mock_env();
+        let info = mock_info("admin", &[]);
+
+        setup_router(deps.as_mut(), &env, &[], RoutingMode::FusionPacket);
+        let snapshot = |current_tick: i64| ClSnapshot {
+            current_sqrt_price: Decimal256::one(),
+            current_tick,
+            current_liquidity: Decimal256::from_ratio(1_000_000u128, 1u8),
+            ticks: vec![],
+        };
+        let mut stable = balancer_pool(3, "osmosis-1", ["uusdc", "uusdt"], 1_000_000);
+        stable.pool_type = PoolType::StableSwap;
+        stable.stable_swap = Some(StableSwapParams { amplification: 100, scaling_factors: vec![] });
+        let mut concentrated = balancer_pool(4, "osmosis-1", ["uatom", "uusdc"], 1_000_000);
+        concentrated.pool_type = PoolType::ConcentratedLiquidity;
+        concentrated.cl_snapshot = Some(snapshot(0));
+        register_pools(
+            deps.as_mut(),
+            &env,
+            [
+                balancer_pool(1, "osmosis-1", ["uatom", "uosmo"], 1_000_000),
+                balancer_pool(2, "osmosis-1", ["uosmo", "ujuno"], 1_000_000),
+                stable,
+                concentrated,
+            ],
+        );
+        let registered_at = env.block.time.seconds();
+
+        // Only the admin manages updaters and staleness
+        let msg = ExecuteMsg::AddPoolUpdater { address: "feeder".to_string() };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = ExecuteMsg::UpdateMaxPoolStaleness { max_pool_staleness: 600 };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        let msg = QueryMsg::ListPoolUpdaters { start_after: None, limit: None };
+        let res: PoolUpdatersResponse = cosmwasm_std::from_json(query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+        assert_eq!(res.updaters, vec!["feeder".to_string()]);
+
+        // Updaters push liquidity, one coin per pool denom
+        env.block.time = env.block.time.plus_seconds(1_000);
+        let feeder = mock_info("feeder", &[]);
+        let msg = ExecuteMsg::UpdatePoolLiquidity {
+            pool_id: 1,
+            liquidity: vec![Coin::new(2_000_000, "uatom")],
+            cl_snapshot: None,
+            scaling_factors: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), feeder.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidInput { .. }));
+
+        let msg = ExecuteMsg::UpdatePoolLiquidity {
+            pool_id: 1,
+            liquidity: vec![Coin::new(2_000_000, "uatom"), Coin::new(1_500_000, "uosmo")],
+            cl_snapshot: None,
+            scaling_factors: None,
+        };
+        let err = execute(deps.as_mut(), env.clone(), mock_info("user", &[]), msg.clone()).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(deps.as_mut(), env.clone(), feeder.clone(), msg).unwrap();
+
+        let pool_info = POOL_REGISTRY.load(&deps.storage, 1).unwrap();
+        assert_eq!(pool_info.liquidity[0], Coin::new(2_000_000, "uatom"));
+        assert_eq!(pool_info.last_updated, Some(env.block.time.seconds()));
+
+        // Liquidity alone leaves stableswap and snapshotted concentrated pools stale
+        let update = |pool_id: u64,
+                      denoms: [&str; 2],
+                      cl_snapshot: Option<ClSnapshot>,
+                      scaling_factors: Option<Vec<u64>>| ExecuteMsg::UpdatePoolLiquidity {
+            pool_id,
+            liquidity: vec![Coin::new(3_000_000, denoms[0]), Coin::new(3_000_000, denoms[1])],
+            cl_snapshot,
+            scaling_factors,
+        };
+        for (pool_id, denoms) in [(3, ["uusdc", "uusdt"]), (4, ["uatom", "uusdc"])] {
+            execute(deps.as_mut(), env.clone(), feeder.clone(), update(pool_id, denoms, None, None)).unwrap();
+            let pool_info = POOL_REGISTRY.load(&deps.storage, pool_id).unwrap();
+            assert_eq!(pool_info.liquidity[0].amount, Uint128::new(3_000_000));
+            assert_eq!(pool_info.last_updated, Some(registered_at));
+        }
+
+        // Pushed along with their pricing inputs, they are up to date
+        let msg = update(3, ["uusdc", "uusdt"], None, Some(vec![1, 1]));
+        execute(deps.as_mut(), env.clone(), feeder.clone(), msg).unwrap();
+        let pool_info = POOL_REGISTRY.load(&deps.storage, 3).unwrap();
+        assert_eq!(pool_info.stable_swap.unwrap().scaling_factors, vec![1, 1]);
+        assert_eq!(pool_info.last_updated, Some(env.block.time.seconds()));
+
+        let msg = update(4, ["uatom", "uusdc"], Some(snapshot(100)), None);
+        execute(deps.as_mut(), env.clone(), feeder.clone(), msg).unwrap();
+        let pool_info = POOL_REGISTRY.load(&deps.storage, 4).unwrap();
+        assert_eq!(pool_info.cl_snapshot, Some(snapshot(100)));
+        assert_eq!(pool_info.last_updated, Some(env.block.time.seconds()));
+
+        // Inputs of another pool type are rejected
+        let msg = update(1, ["uatom", "uosmo"], Some(snapshot(0)), None);
+        let err = execute(deps.as_mut(), env.clone(), feeder.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidInput { .. }));
+        let msg = update(4, ["uatom", "uusdc"], None, Some(vec![1, 1]));
+        let err = execute(deps.as_mut(), env.clone(), feeder.clone(), msg).unwrap_err();
+        assert!(matches!(err, ContractError::InvalidInput { .. }));
+
+        // Pool 2 hasn't been updated for 1000s, past the 600s limit
+        let find_route = |deps: Deps, env: Env| -> RouteResponse {
+            let msg = QueryMsg::FindBestRoute {
+                start_denom: "uatom".to_string(),
+                end_denom: "ujuno".to_string(),
+                amount_in: Uint128::new(1_000),
+                max_hops: None,
+                split: false,
+            };
+            cosmwasm_std::from_json(query(deps, env, msg).unwrap()).unwrap()
+        };
+        assert!(find_route(deps.as_ref(), env.clone()).routes.is_empty());
+
+        let hop = |pool_id: u64, token_in: &str, token_out: &str| HopRoute {
+            chain_id: "osmosis-1".to_string(),
+            pool_id,
+            token_in_denom: token_in.to_string(),
+            token_out_denom: token_out.to_string(),
+        };
+        let msg = ExecuteMsg::ExecuteMultiHopSwap {
+            routes: vec![hop(1, "uatom", "uosmo"), hop(2, "uosmo", "ujuno")],
+            min_output: Uint128::new(1),
+            timeout_timestamp: env.block.time.seconds() + 3600,
+            split: None,
+            receiver: None,
+        };
+        let user_info = mock_info("user", &[Coin::new(1_000, "uatom")]);
+        let err = execute(deps.as_mut(), env.clone(), user_info, msg).unwrap_err();
+        assert!(matches!(err, ContractError::StalePoolData { pool_id: 2 }));
+
+        // Fresh data makes the pool routable again
+        let msg = ExecuteMsg::UpdatePoolLiquidity {
+            pool_id: 2,
+            liquidity: vec![Coin::new(1_000_000, "uosmo"), Coin::new(1_000_000, "ujuno")],
+            cl_snapshot: None,
+            scaling_factors: None,
+        };
+        execute(deps.as_mut(), env.clone(), feeder.clone(), msg).unwrap();
+        let res = find_route(deps.as_ref(), env.clone());
+        assert_eq!(res.routes.len(), 1);
+        assert_eq!(res.routes[0].iter().map(|hop| hop.pool_id).collect::<Vec<_>>(), vec![1, 2]);
+
+        // Removed updaters lose access
+        let msg = ExecuteMsg::RemovePoolUpdater { address: "feeder".to_string() };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let msg = ExecuteMsg::UpdatePoolLiquidity {
+            pool_id: 2,
+            liquidity: vec![Coin::new(1_000_000, "uosmo"), Coin::new(1_000_000, "ujuno")],
+            cl_snapshot: None,
+            scaling_factors: None,
+        };
+        let err = execute(deps.as_mut(), env, feeder, msg).unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+    }
+
+    #[test]
+    fn test_in_flight_swap_refunds() {
+        use crate::ibc::{ibc_packet_ack, ibc_packet_timeout, SEND_PACKET_REPLY_ID, TRANSFER_REPLY_ID};
+        use crate::test_helpers::{
+            balancer_pool, mock_ibc_packet_ack_msg, mock_ibc_packet_timeout_msg, register_pools, setup_router,
+        };
+        use cosmwasm_std::{attr, BankMsg, Event, IbcEndpoint, IbcPacket, SubMsgResponse, SubMsgResult};
+        use osmosis_std::types::ibc::applications::transfer::v1::MsgTransferResponse;
+
+        let mut deps = mock_dependencies();
+        let env = mock_env();
+
+        let chains = [("osmosis-1", "osmo", "channel-0"), ("juno-1", "juno", "channel-1")];
+        let pools = || {
+            [
+                balancer_pool(1, "osmosis-1", ["uatom", "uosmo"], 1_000_000),
+                balancer_pool(2, "juno-1", ["uosmo", "ujuno"], 1_000_000),
+            ]
+        };
+        setup_router(deps.as_mut(), &env, &chains, RoutingMode::FusionPacket);
+        register_pools(deps.as_mut(), &env, pools());
+
+        let hop = |chain_id: &str, pool_id: u64, token_in: &str, token_out: &str| HopRoute {
+            chain_id: chain_id.to_string(),
+            pool_id,
+            token_in_denom: token_in.to_string(),
+            token_out_denom: token_out.to_string(),
+        };
+        let msg = ExecuteMsg::ExecuteMultiHopSwap {
+            routes: vec![hop("osmosis-1", 1, "uatom", "uosmo"), hop("juno-1", 2, "uosmo", "ujuno")],
+            min_output: Uint128::new(100),
+            timeout_timestamp: env.block.time.seconds() + 3600,
+            split: None,
+            receiver: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("user", &[Coin::new(100_000, "uatom")]), msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(res.messages[0].id, SEND_PACKET_REPLY_ID);
+
+        // The send reply records the packet under the sequence the chain assigned
+        let reply_msg = Reply {
+            id: SEND_PACKET_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![Event::new("send_packet").add_attribute("packet_sequence", "7")],
+                data: None,
+            }),
+        };
+        let res = reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+        assert!(res.attributes.contains(&attr("hop_index", "0")));
+
+        let status = |deps: Deps, channel_id: &str, sequence: u64| -> InFlightSwap {
+            let msg = QueryMsg::GetInFlightSwap { channel_id: channel_id.to_string(), sequence };
+            let res: InFlightSwapResponse = cosmwasm_std::from_json(query(deps, mock_env(), msg).unwrap()).unwrap();
+            res.swap
+        };
+        let first = status(deps.as_ref(), "channel-0", 7);
+        assert_eq!(first.sender, "user");
+        assert_eq!((first.denom.as_str(), first.amount), ("uatom", Uint128::new(100_000)));
+        assert_eq!((first.hop_index, first.status), (0, SwapStatus::InFlight));
+
+        let packet = |channel_id: &str, sequence: u64| {
+            IbcPacket::new(
+                Binary::from(b"{}".to_vec()),
+                IbcEndpoint { port_id: "wasm.router".to_string(), channel_id: channel_id.to_string() },
+                IbcEndpoint { port_id: "wasm.remote".to_string(), channel_id: "channel-9".to_string() },
+                sequence,
+                IbcTimeout::with_timestamp(env.block.time.plus_seconds(3600)),
+            )
+        };
+        let ack = |channel_id: &str, sequence: u64, ack: &str| {
+            mock_ibc_packet_ack_msg(packet(channel_id, sequence), Binary::from(ack.as_bytes()), "relayer")
+        };
+
+        // An error ack on the first hop refunds the sender's deposit, once
+        let res = ibc_packet_ack(deps.as_mut(), env.clone(), ack("channel-0", 7, r#"{"error":"swap failed"}"#)).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "user".to_string(),
+                amount: vec![Coin::new(100_000, "uatom")],
+            })
+        );
+        assert!(res.attributes.contains(&attr("hop_index", "0")));
+        assert_eq!(status(deps.as_ref(), "channel-0", 7).status, SwapStatus::Refunded);
+
+        let res = ibc_packet_ack(deps.as_mut(), env.clone(), ack("channel-0", 7, r#"{"error":"swap failed"}"#)).unwrap();
+        assert!(res.messages.is_empty());
+
+        // A timed out packet refunds the sender too
+        let msg = ExecuteMsg::ExecuteMultiHopSwap {
+            routes: vec![hop("osmosis-1", 1, "uatom", "uosmo"), hop("juno-1", 2, "uosmo", "ujuno")],
+            min_output: Uint128::new(100),
+            timeout_timestamp: env.block.time.seconds() + 3600,
+            split: None,
+            receiver: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("user", &[Coin::new(50_000, "uatom")]), msg).unwrap();
+        let reply_msg = Reply {
+            id: SEND_PACKET_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![Event::new("send_packet").add_attribute("packet_sequence", "8")],
+                data: None,
+            }),
+        };
+        reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+        let timeout = mock_ibc_packet_timeout_msg(packet("channel-0", 8), "relayer");
+        let res = ibc_packet_timeout(deps.as_mut(), env.clone(), timeout).unwrap();
+        assert_eq!(
+            res.messages[0].msg,
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "user".to_string(),
+                amount: vec![Coin::new(50_000, "uatom")],
+            })
+        );
+        assert!(res.attributes.contains(&attr("hop_index", "0")));
+        assert_eq!(status(deps.as_ref(), "channel-0", 8).status, SwapStatus::Refunded);
+
+        // Successful acks complete the swap; untracked packets are ignored
+        let msg = ExecuteMsg::ExecuteMultiHopSwap {
+            routes: vec![hop("osmosis-1", 1, "uatom", "uosmo")],
+            min_output: Uint128::new(100),
+            timeout_timestamp: env.block.time.seconds() + 3600,
+            split: None,
+            receiver: None,
+        };
+        execute(deps.as_mut(), env.clone(), mock_info("user", &[Coin::new(1_000, "uatom")]), msg).unwrap();
+        let reply_msg = Reply {
+            id: SEND_PACKET_REPLY_ID,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![Event::new("send_packet").add_attribute("packet_sequence", "9")],
+                data: None,
+            }),
+        };
+        reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+        assert!(PENDING_PACKETS.may_load(&deps.storage).unwrap().is_none());
+        ibc_packet_ack(deps.as_mut(), env.clone(), ack("channel-0", 9, r#"{"success":true}"#)).unwrap();
+        assert_eq!(status(deps.as_ref(), "channel-0", 9).status, SwapStatus::Completed);
+
+        let res = ibc_packet_ack(deps.as_mut(), env.clone(), ack("channel-0", 42, r#"{"success":true}"#)).unwrap();
+        assert!(res.messages.is_empty());
+
+        // ICS-20 routes are tracked from the transfer reply and settled by ibc-hooks callbacks
+        let mut deps = mock_dependencies();
+        setup_router(deps.as_mut(), &env, &chains, RoutingMode::Ics20Pfm);
+        register_pools(deps.as_mut(), &env, pools());
+        let swap = |deps: DepsMut, sequence: u64| {
+            let msg = ExecuteMsg::ExecuteMultiHopSwap {
+                routes: vec![hop("osmosis-1", 1, "uatom", "uosmo"), hop("juno-1", 2, "uosmo", "ujuno")],
+                min_output: Uint128::new(100),
+                timeout_timestamp: env.block.time.seconds() + 3600,
+                split: None,
+                receiver: Some("juno1user".to_string()),
+            };
+            let res = execute(deps, env.clone(), mock_info("user", &[Coin::new(10_000, "uatom")]), msg).unwrap();
+            assert_eq!(res.messages[0].id, TRANSFER_REPLY_ID);
+            Reply {
+                id: TRANSFER_REPLY_ID,
+                result: SubMsgResult::Ok(SubMsgResponse {
+                    events: vec![],
+                    data: Some(MsgTransferResponse { sequence }.into()),
+                }),
+            }
+        };
+        for sequence in [1, 2, 3] {
+            let reply_msg = swap(deps.as_mut(), sequence);
+            let res = reply(deps.as_mut(), env.clone(), reply_msg).unwrap();
+            assert!(res.attributes.contains(&attr("hop_index", "0")));
+            let tracked = status(deps.as_ref(), "channel-0", sequence);
+            assert_eq!((tracked.hop_index, tracked.status), (0, SwapStatus::InFlight));
+        }
+
+        let refund = CosmosMsg::Bank(BankMsg::Send {
+            to_address: "user".to_string(),
+            amount: vec![Coin::new(10_000, "uatom")],
+        });
+
+        // A failed transfer, such as a failed first swap, comes back to the router and is refunded
+        let msg = SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCAck {
+            channel: "channel-0".to_string(),
+            sequence: 1,
+            ack: "".to_string(),
+            success: false,
+        });
+        let res = sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(res.messages[0].msg, refund);
+        assert!(res.attributes.contains(&attr("hop_index", "0")));
+        assert_eq!(status(deps.as_ref(), "channel-0", 1).status, SwapStatus::Refunded);
+
+        let msg = SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCTimeout { channel: "channel-0".to_string(), sequence: 2 });
+        let res = sudo(deps.as_mut(), env.clone(), msg.clone()).unwrap();
+        assert_eq!(res.messages[0].msg, refund);
+        assert!(res.attributes.contains(&attr("hop_index", "0")));
+        assert!(sudo(deps.as_mut(), env.clone(), msg).unwrap().messages.is_empty());
+
+        let msg = SudoMsg::IBCLifecycleComplete(IBCLifecycleComplete::IBCAck {
+            channel: "channel-0".to_string(),
+            sequence: 3,
+            ack: "".to_string(),
+            success: true,
+        });
+        let res = sudo(deps.as_mut(), env, msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(status(deps.as_ref(), "channel-0", 3).status, SwapStatus::Completed);
+    }
+
     #[test]
     fn test_slippage_protection() {
         let mut deps = mock_dependencies();
@@ -784,6 +2426,7 @@ mod tests {
                     chain_prefix: "osmo".to_string(),
                     ibc_channel: "channel-0".to_string(),
                     native_denom: "uosmo".to_string(),
+                    routing_mode: RoutingMode::FusionPacket,
                 },
             ],
             registry_contract: None,
@@ -815,6 +2458,11 @@ mod tests {
             ],
             swap_fee: Decimal::permille(3),
             exit_fee: Decimal::zero(),
+            active: true,
+            weights: vec![],
+            stable_swap: None,
+            cl_snapshot: None,
+            last_updated: None,
         };
         execute(deps.as_mut(), env.clone(), info.clone(), ExecuteMsg::RegisterPool { pool_info: pool }).unwrap();
 
@@ -833,6 +2481,8 @@ mod tests {
             routes,
             min_output: Uint128::new(1500), // Expecting too much output
             timeout_timestamp: env.block.time.seconds() + 3600,
+            split: None,
+            receiver: None,
         };
 
         // Should fail due to slippage
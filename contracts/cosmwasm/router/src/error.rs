@@ -39,6 +39,9 @@ pub enum ContractError {
     #[error("Invalid IBC version: expected {expected}, got {actual}")]
     InvalidIbcVersion { expected: String, actual: String },
 
+    #[error("Pool {pool_id} data is stale")]
+    StalePoolData { pool_id: u64 },
+
     #[error("Invalid input: {msg}")]
     InvalidInput { msg: String },
 }
\ No newline at end of file
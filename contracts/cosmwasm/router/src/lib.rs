@@ -1,11 +1,16 @@
+pub mod concentrated;
 pub mod constants;
 pub mod contract;
 pub mod error;
 pub mod ibc;
 pub mod msg;
+pub mod osmosis;
+pub mod pfm;
 pub mod registry_integration;
 pub mod routing;
+pub mod stableswap;
 pub mod state;
+pub mod weighted;
 
 // Test-only module - not included in production builds
 #[cfg(test)]
@@ -1,5 +1,5 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Coin, Decimal, Uint128};
+use cosmwasm_std::{Coin, Decimal, Decimal256, SignedDecimal256, Uint128};
 use fusion_plus::ProtocolConfig;
 
 #[cw_serde]
@@ -17,6 +17,21 @@ pub struct ChainConfig {
     pub chain_prefix: String,
     pub ibc_channel: String,
     pub native_denom: String,
+    /// How routes entering this chain are executed
+    #[serde(default)]
+    pub routing_mode: RoutingMode,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum RoutingMode {
+    /// Custom packets on the `fusion-router-v1` channel, handled by our router on each chain
+    #[default]
+    FusionPacket,
+    /// ICS-20 transfers with packet-forward-middleware `forward` memos and Osmosis
+    /// ibc-hooks `wasm` swap memos. The registered router of such a chain is its
+    /// crosschain swap contract.
+    Ics20Pfm,
 }
 
 #[cw_serde]
@@ -32,10 +47,60 @@ pub enum ExecuteMsg {
         pool_id: u64,
         pool_info: PoolInfo,
     },
+    RemovePool {
+        pool_id: u64,
+    },
+    /// Inactive pools stay registered but are skipped by routing and estimation
+    SetPoolActive {
+        pool_id: u64,
+        active: bool,
+    },
+    /// Reload a pool's liquidity, fees and type from the local Osmosis chain.
+    /// Anyone may call it. Concentrated pools with a tick snapshot keep their
+    /// last update time, since their ticks aren't reloaded.
+    RefreshPool {
+        pool_id: u64,
+    },
+    RefreshPools {
+        ids: Vec<u64>,
+    },
+    /// Allow `address` to push pool liquidity with `UpdatePoolLiquidity`
+    AddPoolUpdater {
+        address: String,
+    },
+    RemovePoolUpdater {
+        address: String,
+    },
+    /// Replace a pool's liquidity, one coin per pool denom. Open to the admin
+    /// and pool updaters. The pool only counts as updated once all its pricing
+    /// inputs are pushed: a concentrated pool's snapshot, if it has one, and a
+    /// stableswap pool's scaling factors.
+    UpdatePoolLiquidity {
+        pool_id: u64,
+        liquidity: Vec<Coin>,
+        #[serde(default)]
+        cl_snapshot: Option<ClSnapshot>,
+        #[serde(default)]
+        scaling_factors: Option<Vec<u64>>,
+    },
+    /// Set the age, in seconds, past which pool data is too stale to route through
+    UpdateMaxPoolStaleness {
+        max_pool_staleness: u64,
+    },
+    /// Swap along `routes`, or across the legs of `split` when given (with
+    /// `routes` left empty). `min_output` is checked against the sum of the
+    /// legs' minimum outputs. Exactly one coin of the first hop's input denom
+    /// must be sent.
     ExecuteMultiHopSwap {
         routes: Vec<HopRoute>,
         min_output: Uint128,
         timeout_timestamp: u64,
+        #[serde(default)]
+        split: Option<Vec<SplitLeg>>,
+        /// Address on the last hop's chain receiving the output. Required for
+        /// routes sent as ICS-20 transfers; other routes default to the sender.
+        #[serde(default)]
+        receiver: Option<String>,
     },
     RegisterRouter {
         chain_id: String,
@@ -44,11 +109,38 @@ pub enum ExecuteMsg {
     RemoveRouter {
         chain_id: String,
     },
+    /// Register the Osmosis crosschain-swaps contract on `chain_id`. ICS-20
+    /// routes execute their swaps there with its `osmosis_swap` message, which
+    /// fusion routers don't implement.
+    RegisterCrosschainSwaps {
+        chain_id: String,
+        contract_address: String,
+    },
     UpdateRegistryContract {
         registry_contract: Option<String>,
     },
 }
 
+/// Callbacks delivered by the Osmosis ibc-hooks module
+#[cw_serde]
+pub enum SudoMsg {
+    #[serde(rename = "ibc_lifecycle_complete")]
+    IBCLifecycleComplete(IBCLifecycleComplete),
+}
+
+#[cw_serde]
+pub enum IBCLifecycleComplete {
+    #[serde(rename = "ibc_ack")]
+    IBCAck {
+        channel: String,
+        sequence: u64,
+        ack: String,
+        success: bool,
+    },
+    #[serde(rename = "ibc_timeout")]
+    IBCTimeout { channel: String, sequence: u64 },
+}
+
 #[cw_serde]
 #[derive(QueryResponses)]
 pub enum QueryMsg {
@@ -61,12 +153,31 @@ pub enum QueryMsg {
     #[returns(PoolInfoResponse)]
     GetPoolInfo { pool_id: u64 },
     
+    /// Registered pools, optionally only those on `chain_id` and/or containing `denom`
+    #[returns(ListPoolsResponse)]
+    ListPools {
+        chain_id: Option<String>,
+        denom: Option<String>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    
+    /// Addresses allowed to push pool liquidity
+    #[returns(PoolUpdatersResponse)]
+    ListPoolUpdaters {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    
     #[returns(RouteResponse)]
     FindBestRoute {
         start_denom: String,
         end_denom: String,
         amount_in: Uint128,
         max_hops: Option<u32>,
+        /// Also plan a split of `amount_in` across the returned routes
+        #[serde(default)]
+        split: bool,
     },
     
     #[returns(EstimateResponse)]
@@ -74,6 +185,13 @@ pub enum QueryMsg {
         routes: Vec<HopRoute>,
         amount_in: Uint128,
     },
+    
+    /// Status of a packet sent by `ExecuteMultiHopSwap`
+    #[returns(InFlightSwapResponse)]
+    GetInFlightSwap {
+        channel_id: String,
+        sequence: u64,
+    },
 }
 
 #[cw_serde]
@@ -85,6 +203,54 @@ pub struct PoolInfo {
     pub liquidity: Vec<Coin>,
     pub swap_fee: Decimal,
     pub exit_fee: Decimal,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    /// `PoolType::Balancer` token weights in `token_denoms` order; empty means
+    /// equal weights
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weights: Vec<Uint128>,
+    /// Curve parameters, required for `PoolType::StableSwap`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stable_swap: Option<StableSwapParams>,
+    /// Tick state for `PoolType::ConcentratedLiquidity`; without it swaps are
+    /// approximated from the balances
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cl_snapshot: Option<ClSnapshot>,
+    /// Block time, in seconds, the pool data was last written; set by the contract
+    #[serde(default)]
+    pub last_updated: Option<u64>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[cw_serde]
+pub struct StableSwapParams {
+    /// Amplification coefficient `A`
+    pub amplification: u64,
+    /// One factor per token in `token_denoms` order; a token's amount is divided
+    /// by its factor before entering the invariant. Empty means all 1.
+    #[serde(default)]
+    pub scaling_factors: Vec<u64>,
+}
+
+/// Concentrated-liquidity pool state, priced as the second token per the first
+#[cw_serde]
+pub struct ClSnapshot {
+    pub current_sqrt_price: Decimal256,
+    pub current_tick: i64,
+    /// Liquidity active in the current tick range
+    pub current_liquidity: Decimal256,
+    /// Initialized ticks around the current one
+    pub ticks: Vec<TickLiquidity>,
+}
+
+#[cw_serde]
+pub struct TickLiquidity {
+    pub tick_index: i64,
+    /// Liquidity added when the price crosses this tick upwards
+    pub liquidity_net: SignedDecimal256,
 }
 
 #[cw_serde]
@@ -118,12 +284,76 @@ pub struct PoolInfoResponse {
     pub pool_info: PoolInfo,
 }
 
+#[cw_serde]
+pub struct ListPoolsResponse {
+    pub pools: Vec<PoolInfo>,
+}
+
+#[cw_serde]
+pub struct PoolUpdatersResponse {
+    pub updaters: Vec<String>,
+}
+
+/// Packet of a route sent by `ExecuteMultiHopSwap`, a fusion packet or an ICS-20
+/// transfer, tracked with the hop's input until it is acknowledged or times out
+#[cw_serde]
+pub struct InFlightSwap {
+    pub sender: String,
+    /// Index within its route of the hop the packet was sent for
+    pub hop_index: u32,
+    pub channel_id: String,
+    pub denom: String,
+    pub amount: Uint128,
+    pub status: SwapStatus,
+}
+
+#[cw_serde]
+pub enum SwapStatus {
+    /// Sent, awaiting its acknowledgement or timeout
+    InFlight,
+    Completed,
+    /// Failed or timed out, and the sender was refunded
+    Refunded,
+}
+
+#[cw_serde]
+pub struct InFlightSwapResponse {
+    pub swap: InFlightSwap,
+}
+
 #[cw_serde]
 pub struct RouteResponse {
     pub routes: Vec<Vec<HopRoute>>,
     pub estimated_output: Uint128,
     pub total_fees: Decimal,
     pub price_impact: Decimal,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split_plan: Option<SplitPlan>,
+}
+
+/// Split of `amount_in` across non-overlapping routes
+#[cw_serde]
+pub struct SplitPlan {
+    pub legs: Vec<SplitRoute>,
+    pub estimated_output: Uint128,
+}
+
+#[cw_serde]
+pub struct SplitRoute {
+    pub route: Vec<HopRoute>,
+    /// Percentage of `amount_in` sent down this route
+    pub percent: u8,
+    pub amount_in: Uint128,
+    pub estimated_output: Uint128,
+}
+
+/// One leg of a split swap
+#[cw_serde]
+pub struct SplitLeg {
+    pub route: Vec<HopRoute>,
+    /// Percentage of the sent funds; the legs must add up to 100
+    pub percent: u8,
+    pub min_output: Uint128,
 }
 
 #[cw_serde]
@@ -0,0 +1,128 @@
+//! Pool state read from the local Osmosis poolmanager and gamm modules.
+
+use std::str::FromStr;
+
+use cosmwasm_std::{Binary, Coin, Decimal, Deps, StdError, StdResult, Uint128};
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool as ConcentratedPool;
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::Pool as StableSwapPool;
+use osmosis_std::types::osmosis::gamm::v1beta1::Pool as BalancerPool;
+use fusion_plus::{
+    parse_osmosis_dec, parse_osmosis_dec256, BALANCER_POOL_TYPE_URL, CONCENTRATED_POOL_TYPE_URL,
+    OSMOSIS_BIGDEC_PRECISION, OSMOSIS_DEC_PRECISION, STABLESWAP_POOL_TYPE_URL,
+};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::PoolmanagerQuerier;
+
+use crate::msg::{PoolInfo, PoolType, StableSwapParams};
+
+/// Return `pool_info` with its type, liquidity, swap fee and weights or
+/// scaling factors replaced by the pool's current on-chain state.
+///
+/// Osmosis stableswap pools have no amplification coefficient, so the one
+/// already configured is kept. Concentrated pools with a tick snapshot get its
+/// price, tick and active liquidity updated. Their initialized ticks aren't
+/// queried and are kept, so such pools need a snapshot pushed to count as fresh.
+pub fn refresh_pool_info(deps: Deps, pool_info: &PoolInfo) -> StdResult<PoolInfo> {
+    let pool_id = pool_info.pool_id;
+    let poolmanager = PoolmanagerQuerier::new(&deps.querier);
+
+    let pool = poolmanager
+        .pool(pool_id)
+        .map_err(|e| StdError::generic_err(format!("Failed to query pool {}: {}", pool_id, e)))?
+        .pool
+        .ok_or_else(|| StdError::generic_err(format!("Pool {} not found", pool_id)))?;
+
+    let liquidity = poolmanager
+        .total_pool_liquidity(pool_id)
+        .map_err(|e| StdError::generic_err(format!("Failed to query liquidity of pool {}: {}", pool_id, e)))?
+        .liquidity
+        .into_iter()
+        .map(|coin| Ok(Coin::new(Uint128::from_str(&coin.amount)?.u128(), coin.denom)))
+        .collect::<StdResult<Vec<Coin>>>()?;
+
+    let mut refreshed = pool_info.clone();
+    for denom in &pool_info.token_denoms {
+        if !liquidity.iter().any(|coin| &coin.denom == denom) {
+            return Err(StdError::generic_err(format!("Pool {} has no {} liquidity", pool_id, denom)));
+        }
+    }
+    refreshed.liquidity = liquidity;
+
+    let value = Binary::from(pool.value);
+    match pool.type_url.as_str() {
+        BALANCER_POOL_TYPE_URL => {
+            let pool = BalancerPool::try_from(value)?;
+            let params = pool
+                .pool_params
+                .ok_or_else(|| StdError::generic_err(format!("Pool {} has no pool params", pool_id)))?;
+
+            // Weights follow `token_denoms`
+            let weights = pool_info
+                .token_denoms
+                .iter()
+                .map(|denom| {
+                    let asset = pool
+                        .pool_assets
+                        .iter()
+                        .find(|asset| asset.token.as_ref().map(|t| &t.denom) == Some(denom))
+                        .ok_or_else(|| StdError::generic_err(format!("Pool {} has no {} asset", pool_id, denom)))?;
+                    Uint128::from_str(&asset.weight)
+                })
+                .collect::<StdResult<Vec<_>>>()?;
+
+            refreshed.pool_type = PoolType::Balancer;
+            refreshed.swap_fee = parse_osmosis_dec(&params.swap_fee)?;
+            refreshed.exit_fee = parse_osmosis_dec(&params.exit_fee)?;
+            refreshed.weights = weights;
+        }
+        STABLESWAP_POOL_TYPE_URL => {
+            let pool = StableSwapPool::try_from(value)?;
+            let params = pool
+                .pool_params
+                .ok_or_else(|| StdError::generic_err(format!("Pool {} has no pool params", pool_id)))?;
+            let amplification = pool_info
+                .stable_swap
+                .as_ref()
+                .map(|params| params.amplification)
+                .ok_or_else(|| {
+                    StdError::generic_err(format!("StableSwap pool {} has no amplification configured", pool_id))
+                })?;
+
+            // Scaling factors follow the pool's liquidity, reordered to `token_denoms`
+            let scaling_factors = pool_info
+                .token_denoms
+                .iter()
+                .map(|denom| {
+                    pool.pool_liquidity
+                        .iter()
+                        .position(|coin| &coin.denom == denom)
+                        .and_then(|i| pool.scaling_factors.get(i).copied())
+                        .ok_or_else(|| StdError::generic_err(format!("Pool {} has no {} scaling factor", pool_id, denom)))
+                })
+                .collect::<StdResult<Vec<_>>>()?;
+
+            refreshed.pool_type = PoolType::StableSwap;
+            refreshed.swap_fee = parse_osmosis_dec(&params.swap_fee)?;
+            refreshed.exit_fee = parse_osmosis_dec(&params.exit_fee)?;
+            refreshed.stable_swap = Some(StableSwapParams { amplification, scaling_factors });
+        }
+        CONCENTRATED_POOL_TYPE_URL => {
+            let pool = ConcentratedPool::try_from(value)?;
+            refreshed.pool_type = PoolType::ConcentratedLiquidity;
+            refreshed.swap_fee = parse_osmosis_dec(&pool.spread_factor)?;
+            refreshed.exit_fee = Decimal::zero();
+            if let Some(snapshot) = refreshed.cl_snapshot.as_mut() {
+                snapshot.current_sqrt_price = parse_osmosis_dec256(&pool.current_sqrt_price, OSMOSIS_BIGDEC_PRECISION)?;
+                snapshot.current_tick = pool.current_tick;
+                snapshot.current_liquidity = parse_osmosis_dec256(&pool.current_tick_liquidity, OSMOSIS_DEC_PRECISION)?;
+            }
+        }
+        other => {
+            return Err(StdError::generic_err(format!(
+                "Unsupported pool type for pool {}: {}",
+                pool_id, other
+            )))
+        }
+    }
+
+    Ok(refreshed)
+}
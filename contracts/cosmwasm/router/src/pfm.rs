@@ -0,0 +1,137 @@
+//! Memos for routes executed as standard ICS-20 transfers.
+//!
+//! Each swap hop is an Osmosis ibc-hooks `wasm` memo calling the crosschain swap
+//! contract on that chain. Its output comes back to this chain, where
+//! packet-forward-middleware `forward`s it to the next swap chain. The transfer
+//! leaving this chain also asks ibc-hooks for an `ibc_callback`, so its ack or
+//! timeout is reported back and a failed route refunded.
+
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{StdError, StdResult, Uint128};
+use serde::{Deserialize, Serialize};
+
+/// What to do with the swap output if delivering it fails
+pub const ON_FAILED_DELIVERY: &str = "do_nothing";
+
+#[cw_serde]
+pub enum Memo {
+    Forward(ForwardMemo),
+    Wasm(WasmMemo),
+}
+
+/// packet-forward-middleware forward instruction
+#[cw_serde]
+pub struct ForwardMemo {
+    pub receiver: String,
+    pub port: String,
+    pub channel: String,
+    /// Duration string, e.g. "300s"
+    pub timeout: String,
+    pub retries: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<Box<Memo>>,
+}
+
+/// ibc-hooks contract call, executed with the transferred funds
+#[cw_serde]
+pub struct WasmMemo {
+    pub contract: String,
+    pub msg: SwapMsg,
+}
+
+#[cw_serde]
+pub enum SwapMsg {
+    OsmosisSwap {
+        output_denom: String,
+        slippage: Slippage,
+        receiver: String,
+        on_failed_delivery: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        next_memo: Option<Box<Memo>>,
+    },
+}
+
+#[cw_serde]
+pub enum Slippage {
+    MinOutputAmount(Uint128),
+}
+
+/// Memo of the transfer sent from this chain: the first hop's memo, plus the
+/// contract ibc-hooks calls back with the transfer's ack or timeout.
+/// `cw_serde` denies unknown fields, which `flatten` doesn't support.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TransferMemo {
+    #[serde(flatten)]
+    pub memo: Memo,
+    pub ibc_callback: String,
+}
+
+impl Memo {
+    pub fn to_json_string(&self) -> StdResult<String> {
+        serde_json::to_string(self)
+            .map_err(|e| StdError::generic_err(format!("Failed to serialize memo: {}", e)))
+    }
+}
+
+impl TransferMemo {
+    pub fn to_json_string(&self) -> StdResult<String> {
+        serde_json::to_string(self)
+            .map_err(|e| StdError::generic_err(format!("Failed to serialize memo: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memo_format() {
+        let memo = Memo::Forward(ForwardMemo {
+            receiver: "juno1router".to_string(),
+            port: "transfer".to_string(),
+            channel: "channel-1".to_string(),
+            timeout: "300s".to_string(),
+            retries: 0,
+            next: Some(Box::new(Memo::Wasm(WasmMemo {
+                contract: "juno1router".to_string(),
+                msg: SwapMsg::OsmosisSwap {
+                    output_denom: "ujuno".to_string(),
+                    slippage: Slippage::MinOutputAmount(Uint128::new(100)),
+                    receiver: "osmo1receiver".to_string(),
+                    on_failed_delivery: ON_FAILED_DELIVERY.to_string(),
+                    next_memo: None,
+                },
+            }))),
+        });
+
+        assert_eq!(
+            memo.to_json_string().unwrap(),
+            r#"{"forward":{"receiver":"juno1router","port":"transfer","channel":"channel-1","timeout":"300s","retries":0,"next":{"wasm":{"contract":"juno1router","msg":{"osmosis_swap":{"output_denom":"ujuno","slippage":{"min_output_amount":"100"},"receiver":"osmo1receiver","on_failed_delivery":"do_nothing"}}}}}}"#
+        );
+    }
+
+    #[test]
+    fn test_transfer_memo_format() {
+        let memo = TransferMemo {
+            memo: Memo::Wasm(WasmMemo {
+                contract: "osmo1router".to_string(),
+                msg: SwapMsg::OsmosisSwap {
+                    output_denom: "uosmo".to_string(),
+                    slippage: Slippage::MinOutputAmount(Uint128::new(100)),
+                    receiver: "osmosis-1/osmo1receiver".to_string(),
+                    on_failed_delivery: ON_FAILED_DELIVERY.to_string(),
+                    next_memo: None,
+                },
+            }),
+            ibc_callback: "cosmos1contract".to_string(),
+        };
+
+        // The callback sits next to the hop's memo, at the top level
+        let json = memo.to_json_string().unwrap();
+        assert_eq!(
+            json,
+            r#"{"wasm":{"contract":"osmo1router","msg":{"osmosis_swap":{"output_denom":"uosmo","slippage":{"min_output_amount":"100"},"receiver":"osmosis-1/osmo1receiver","on_failed_delivery":"do_nothing"}}},"ibc_callback":"cosmos1contract"}"#
+        );
+        assert_eq!(serde_json::from_str::<TransferMemo>(&json).unwrap(), memo);
+    }
+}
@@ -1,7 +1,12 @@
-use cosmwasm_std::{Decimal, Deps, StdError, StdResult, Uint128};
-use std::collections::{HashSet, VecDeque};
-use crate::msg::{HopRoute, PoolInfo, PoolType};
-use crate::state::{POOL_REGISTRY, load_protocol_config};
+use cosmwasm_std::{Decimal, Deps, Order, StdError, StdResult, Uint128};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use crate::concentrated;
+use crate::constants::{MAX_ROUTES_RETURNED, SPLIT_STEP_PERCENT};
+use crate::msg::{HopRoute, PoolInfo, PoolType, SplitPlan, SplitRoute};
+use crate::stableswap;
+use crate::weighted;
+use crate::state::{POOL_DENOMS, POOL_REGISTRY, load_protocol_config};
 
 #[derive(Clone, Debug)]
 pub struct RouteNode {
@@ -11,116 +16,178 @@ pub struct RouteNode {
     pub total_fee: Decimal,
 }
 
-/// Find the best routes between two tokens
+/// Find the highest-output routes between two tokens, best first.
+///
+/// Routes are extended one hop at a time. At each depth only the
+/// `MAX_ROUTES_RETURNED` best partial routes into each denom are kept, so a
+/// poor route that reaches an intermediate token first no longer hides better
+/// routes through the same token. A route never revisits a denom or reuses a
+/// pool. Every pool evaluation counts against `max_routes_to_explore`, which
+/// bounds the gas spent; routes completed before the budget runs out are
+/// still returned.
+///
+/// The result is ranked by output and pool-disjoint: a route sharing a pool
+/// with a better ranked route is dropped. Pools whose data is older than
+/// `max_pool_staleness` at block time `now` are left out.
 pub fn find_best_routes(
     deps: Deps,
+    now: u64,
     start_denom: String,
     end_denom: String,
     amount_in: Uint128,
     max_hops: Option<u32>,
 ) -> StdResult<Vec<RouteNode>> {
-    // Load configuration to get max hops
+    // Load configuration to get max hops and the exploration budget
     let config = load_protocol_config(deps.storage)?;
     let max_hops = max_hops.unwrap_or(config.routing.max_hops as u32).min(config.routing.max_hops as u32);
-    
-    // BFS to find all possible routes
-    let mut queue = VecDeque::new();
-    let mut visited = HashSet::new();
-    let mut all_routes = Vec::new();
-    
-    // Start node
-    let start_node = RouteNode {
+    let mut budget = config.routing.max_routes_to_explore;
+
+    // Pools are loaded once per denom; BTreeMap keeps the search deterministic
+    let mut pools_by_denom: BTreeMap<String, Vec<PoolInfo>> = BTreeMap::new();
+    let mut frontier = vec![RouteNode {
         denom: start_denom.clone(),
         amount: amount_in,
         path: vec![],
         total_fee: Decimal::zero(),
-    };
-    
-    queue.push_back(start_node);
-    visited.insert(start_denom.clone());
-    
-    while let Some(node) = queue.pop_front() {
-        if node.path.len() >= max_hops as usize {
-            continue;
-        }
-        
-        // Find all pools containing the current denom
-        let pools = find_pools_with_denom(deps, &node.denom)?;
-        
-        for pool_id in pools {
-            let pool_info = POOL_REGISTRY.load(deps.storage, pool_id)?;
-            
-            // Find the other denoms in the pool
-            for other_denom in &pool_info.token_denoms {
-                if other_denom == &node.denom || visited.contains(other_denom) {
-                    continue;
-                }
-                
-                // Calculate output amount for this hop
-                let (amount_out, fee) = calculate_swap_output(
-                    &pool_info,
+    }];
+    let mut complete = Vec::new();
+
+    'search: for _ in 0..max_hops {
+        let mut next: BTreeMap<String, Vec<RouteNode>> = BTreeMap::new();
+
+        for node in &frontier {
+            if !pools_by_denom.contains_key(&node.denom) {
+                let pools = load_routable_pools_with_denom(
+                    deps,
                     &node.denom,
-                    other_denom,
-                    node.amount,
+                    now,
+                    config.routing.max_pool_staleness,
                 )?;
-                
-                // Create new route node
-                let mut new_path = node.path.clone();
-                new_path.push(HopRoute {
-                    chain_id: pool_info.chain_id.clone(),
-                    pool_id: pool_info.pool_id,
-                    token_in_denom: node.denom.clone(),
-                    token_out_denom: other_denom.clone(),
-                });
-                
-                let new_node = RouteNode {
-                    denom: other_denom.clone(),
-                    amount: amount_out,
-                    path: new_path,
-                    total_fee: node.total_fee + fee,
-                };
-                
-                // If we reached the target denom, save the route
-                if other_denom == &end_denom {
-                    all_routes.push(new_node);
-                } else {
-                    // Continue exploring
-                    queue.push_back(new_node);
-                    if all_routes.len() < config.routing.max_routes_to_explore as usize {
-                        visited.insert(other_denom.clone());
+                pools_by_denom.insert(node.denom.clone(), pools);
+            }
+
+            for pool_info in &pools_by_denom[&node.denom] {
+                if node.path.iter().any(|hop| hop.pool_id == pool_info.pool_id) {
+                    continue;
+                }
+
+                for other_denom in &pool_info.token_denoms {
+                    if other_denom == &node.denom
+                        || other_denom == &start_denom
+                        || node.path.iter().any(|hop| &hop.token_out_denom == other_denom)
+                    {
+                        continue;
+                    }
+
+                    if budget == 0 {
+                        break 'search;
+                    }
+                    budget -= 1;
+
+                    // Calculate output amount for this hop
+                    let (amount_out, fee) = calculate_swap_output(
+                        pool_info,
+                        &node.denom,
+                        other_denom,
+                        node.amount,
+                    )?;
+                    if amount_out.is_zero() {
+                        continue;
+                    }
+
+                    let mut path = node.path.clone();
+                    path.push(HopRoute {
+                        chain_id: pool_info.chain_id.clone(),
+                        pool_id: pool_info.pool_id,
+                        token_in_denom: node.denom.clone(),
+                        token_out_denom: other_denom.clone(),
+                    });
+
+                    let new_node = RouteNode {
+                        denom: other_denom.clone(),
+                        amount: amount_out,
+                        path,
+                        total_fee: node.total_fee + fee,
+                    };
+
+                    if other_denom == &end_denom {
+                        complete.push(new_node);
+                    } else {
+                        next.entry(other_denom.clone()).or_default().push(new_node);
                     }
                 }
             }
         }
+
+        // Keep only the best partial routes into each denom
+        frontier = next
+            .into_values()
+            .flat_map(|mut nodes| {
+                nodes.sort_by_key(|node| Reverse(node.amount));
+                nodes.truncate(MAX_ROUTES_RETURNED);
+                nodes
+            })
+            .collect();
+        if frontier.is_empty() {
+            break;
+        }
     }
-    
-    // Sort routes by output amount (descending)
-    all_routes.sort_by(|a, b| b.amount.cmp(&a.amount));
-    
-    // Return top routes
-    Ok(all_routes.into_iter().take(5).collect())
+
+    Ok(rank_routes(complete, MAX_ROUTES_RETURNED))
 }
 
-/// Find all pools containing a specific denom
-fn find_pools_with_denom(deps: Deps, denom: &str) -> StdResult<Vec<u64>> {
-    let mut pools = HashSet::new();
-    
-    // We need to iterate through all registered pools and check if they contain the denom
-    // In a real implementation, you would maintain a reverse index for efficiency
-    // For now, we'll check a limited range of pool IDs
-    
-    // Check pools within configured range (in production, you'd track the max pool ID)
-    // Load configuration for pool discovery range
-    let config = load_protocol_config(deps.storage)?;
-    for pool_id in config.routing.pool_discovery_range.start..=config.routing.pool_discovery_range.end {
-        if let Some(pool_info) = POOL_REGISTRY.may_load(deps.storage, pool_id)? {
-            if pool_info.token_denoms.contains(&denom.to_string()) {
-                pools.insert(pool_id);
-            }
+/// Sort routes by output (descending) and keep the best `limit` that share no pools
+fn rank_routes(mut routes: Vec<RouteNode>, limit: usize) -> Vec<RouteNode> {
+    // Stable sort, so equal outputs keep discovery order
+    routes.sort_by_key(|route| Reverse(route.amount));
+
+    let mut used_pools = HashSet::new();
+    let mut ranked = Vec::new();
+    for route in routes {
+        if ranked.len() >= limit {
+            break;
+        }
+        if route.path.iter().any(|hop| used_pools.contains(&hop.pool_id)) {
+            continue;
         }
+        used_pools.extend(route.path.iter().map(|hop| hop.pool_id));
+        ranked.push(route);
+    }
+    ranked
+}
+
+/// Load the active, fresh pools containing a specific denom
+fn load_routable_pools_with_denom(
+    deps: Deps,
+    denom: &str,
+    now: u64,
+    max_staleness: u64,
+) -> StdResult<Vec<PoolInfo>> {
+    let mut pools = Vec::new();
+    for pool_id in find_pools_with_denom(deps, denom)? {
+        let pool_info = POOL_REGISTRY.load(deps.storage, pool_id)?;
+        if pool_info.active && is_pool_fresh(&pool_info, now, max_staleness) {
+            pools.push(pool_info);
+        }
+    }
+    Ok(pools)
+}
+
+/// Whether a pool's data was written within `max_staleness` seconds of `now`.
+/// Pools that were never stamped count as stale.
+pub fn is_pool_fresh(pool_info: &PoolInfo, now: u64, max_staleness: u64) -> bool {
+    match pool_info.last_updated {
+        Some(updated) => now.saturating_sub(updated) <= max_staleness,
+        None => false,
     }
-    
-    Ok(pools.into_iter().collect())
+}
+
+/// Find all pools containing a specific denom
+fn find_pools_with_denom(deps: Deps, denom: &str) -> StdResult<Vec<u64>> {
+    POOL_DENOMS
+        .prefix(denom)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect()
 }
 
 /// Calculate output amount for a swap in a pool
@@ -147,23 +214,61 @@ pub fn calculate_swap_output(
     // Calculate output based on pool type
     let amount_out = match pool_info.pool_type {
         PoolType::Balancer => {
-            // Simplified constant product formula
-            // amount_out = balance_out * (1 - (balance_in / (balance_in + amount_in_with_fee)))
-            let ratio = Decimal::from_ratio(balance_in, balance_in + amount_in_with_fee);
-            let out_ratio = Decimal::one() - ratio;
-            balance_out * out_ratio
+            let weight = |denom: &str| -> StdResult<Uint128> {
+                if pool_info.weights.is_empty() {
+                    return Ok(Uint128::one());
+                }
+                pool_info.token_denoms.iter()
+                    .position(|d| d == denom)
+                    .and_then(|i| pool_info.weights.get(i).copied())
+                    .ok_or_else(|| StdError::generic_err(format!("No weight for {} in pool", denom)))
+            };
+            weighted::swap_output(
+                balance_in,
+                weight(token_in_denom)?,
+                balance_out,
+                weight(token_out_denom)?,
+                amount_in_with_fee,
+            )?
         }
         PoolType::StableSwap => {
-            // Simplified stable swap formula (assumes 1:1 for stablecoins)
-            // In reality, this would use a more complex curve
-            amount_in_with_fee
-        }
-        PoolType::ConcentratedLiquidity => {
-            // Simplified CL formula
-            // Would need tick information and current price in production
-            let price = Decimal::from_ratio(balance_out, balance_in);
-            amount_in_with_fee * price
+            let params = pool_info.stable_swap.as_ref().ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "StableSwap pool {} has no amplification configured",
+                    pool_info.pool_id
+                ))
+            })?;
+            // The invariant works on balances in `token_denoms` order, matching the scaling factors
+            let balances = pool_info.token_denoms.iter()
+                .map(|denom| pool_info.liquidity.iter()
+                    .find(|c| &c.denom == denom)
+                    .map(|c| c.amount)
+                    .ok_or_else(|| StdError::generic_err(format!("No liquidity for {} in pool", denom))))
+                .collect::<StdResult<Vec<_>>>()?;
+            let position = |denom: &str| pool_info.token_denoms.iter().position(|d| d == denom)
+                .ok_or_else(|| StdError::generic_err(format!("{} not in pool", denom)));
+
+            stableswap::swap_output(
+                &balances,
+                &params.scaling_factors,
+                params.amplification,
+                position(token_in_denom)?,
+                position(token_out_denom)?,
+                amount_in_with_fee,
+            )?
         }
+        PoolType::ConcentratedLiquidity => match &pool_info.cl_snapshot {
+            // Prices are quoted as the second token per the first
+            Some(snapshot) => {
+                let zero_for_one = pool_info.token_denoms.first().map(String::as_str) == Some(token_in_denom);
+                concentrated::swap_output(snapshot, zero_for_one, amount_in_with_fee)?
+            }
+            None => {
+                // Without tick data, approximate from the balance ratio
+                let price = Decimal::from_ratio(balance_out, balance_in);
+                amount_in_with_fee * price
+            }
+        },
     };
     
     Ok((amount_out, pool_info.swap_fee))
@@ -180,6 +285,9 @@ pub fn estimate_multi_hop_swap(
     
     for hop in routes {
         let pool_info = POOL_REGISTRY.load(deps.storage, hop.pool_id)?;
+        if !pool_info.active {
+            return Err(StdError::generic_err(format!("Pool {} is inactive", hop.pool_id)));
+        }
         
         let (amount_out, fee) = calculate_swap_output(
             &pool_info,
@@ -195,6 +303,84 @@ pub fn estimate_multi_hop_swap(
     Ok((current_amount, total_fee))
 }
 
+/// Split `amount_in` across pool-disjoint routes to maximize total output.
+///
+/// The amount is handed out in `SPLIT_STEP_PERCENT` steps, each going to the
+/// route whose output grows most from it. Route outputs are concave in their
+/// input, so the greedy allocation is optimal on that grid. Routes that get
+/// nothing are left out of the plan.
+pub fn find_split_plan(
+    deps: Deps,
+    routes: &[RouteNode],
+    amount_in: Uint128,
+) -> StdResult<SplitPlan> {
+    let mut percents = vec![0u8; routes.len()];
+    let mut outputs = vec![Uint128::zero(); routes.len()];
+
+    if !routes.is_empty() {
+        for _ in 0..(100 / SPLIT_STEP_PERCENT) {
+            let mut best: Option<(usize, Uint128, Uint128)> = None;
+            for (i, route) in routes.iter().enumerate() {
+                let amount = amount_in.multiply_ratio(percents[i] + SPLIT_STEP_PERCENT, 100u128);
+                let (output, _) = estimate_multi_hop_swap(deps, route.path.clone(), amount)?;
+                let gain = output.saturating_sub(outputs[i]);
+                let better = match best {
+                    Some((_, best_gain, _)) => gain > best_gain,
+                    None => true,
+                };
+                if better {
+                    best = Some((i, gain, output));
+                }
+            }
+            if let Some((i, _, output)) = best {
+                percents[i] += SPLIT_STEP_PERCENT;
+                outputs[i] = output;
+            }
+        }
+    }
+
+    // Re-estimate with the exact amounts execution will send
+    let amounts = split_amounts(amount_in, &percents);
+    let mut legs = Vec::new();
+    let mut estimated_output = Uint128::zero();
+    for ((route, percent), amount) in routes.iter().zip(percents).zip(amounts) {
+        if percent == 0 {
+            continue;
+        }
+        let (output, _) = estimate_multi_hop_swap(deps, route.path.clone(), amount)?;
+        estimated_output += output;
+        legs.push(SplitRoute {
+            route: route.path.clone(),
+            percent,
+            amount_in: amount,
+            estimated_output: output,
+        });
+    }
+
+    Ok(SplitPlan { legs, estimated_output })
+}
+
+/// Divide `amount_in` by percentage. The last funded share takes the rounding
+/// remainder, so the amounts always add up to `amount_in` when the percentages
+/// add up to 100.
+pub fn split_amounts(amount_in: Uint128, percents: &[u8]) -> Vec<Uint128> {
+    let last = percents.iter().rposition(|percent| *percent > 0);
+    let mut remaining = amount_in;
+    percents
+        .iter()
+        .enumerate()
+        .map(|(i, percent)| {
+            let amount = if Some(i) == last {
+                remaining
+            } else {
+                amount_in.multiply_ratio(*percent, 100u128)
+            };
+            remaining = remaining.saturating_sub(amount);
+            amount
+        })
+        .collect()
+}
+
 /// Calculate price impact for a swap route
 pub fn calculate_price_impact(
     deps: Deps,
@@ -225,6 +411,7 @@ pub fn calculate_price_impact(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::msg::StableSwapParams;
     use cosmwasm_std::Decimal;
     
     #[test]
@@ -246,6 +433,11 @@ mod tests {
             ],
             swap_fee: Decimal::permille(3), // 0.3%
             exit_fee: Decimal::zero(),
+            active: true,
+            weights: vec![],
+            stable_swap: None,
+            cl_snapshot: None,
+            last_updated: None,
         };
         
         let (amount_out, fee) = calculate_swap_output(
@@ -261,6 +453,41 @@ mod tests {
         assert_eq!(fee, Decimal::permille(3));
     }
     
+    #[test]
+    fn test_calculate_swap_output_weighted() {
+        let pool_info = PoolInfo {
+            pool_id: 4,
+            chain_id: "osmosis-1".to_string(),
+            pool_type: PoolType::Balancer,
+            token_denoms: vec!["uatom".to_string(), "uosmo".to_string(), "ujuno".to_string()],
+            liquidity: vec![
+                cosmwasm_std::Coin::new(5_000_000, "uatom"),
+                cosmwasm_std::Coin::new(3_000_000, "uosmo"),
+                cosmwasm_std::Coin::new(2_000_000, "ujuno"),
+            ],
+            swap_fee: Decimal::zero(),
+            exit_fee: Decimal::zero(),
+            active: true,
+            weights: vec![Uint128::new(50), Uint128::new(30), Uint128::new(20)],
+            stable_swap: None,
+            cl_snapshot: None,
+            last_updated: None,
+        };
+
+        // Any pair of a 50/30/20 pool, against 60-digit references
+        let cases = [
+            ("uatom", "ujuno", 250_000, 229_659),
+            ("ujuno", "uosmo", 100_000, 96_010),
+        ];
+        for (token_in, token_out, amount_in, expected) in cases {
+            let (amount_out, _) = calculate_swap_output(&pool_info, token_in, token_out, Uint128::new(amount_in)).unwrap();
+            assert!(
+                amount_out.u128().abs_diff(expected) <= 1,
+                "{} -> {}: expected {}, got {}", token_in, token_out, expected, amount_out,
+            );
+        }
+    }
+
     #[test]
     fn test_calculate_swap_output_stableswap() {
         let pool_info = PoolInfo {
@@ -280,6 +507,14 @@ mod tests {
             ],
             swap_fee: Decimal::permille(1), // 0.1%
             exit_fee: Decimal::zero(),
+            active: true,
+            weights: vec![],
+            stable_swap: Some(StableSwapParams {
+                amplification: 100,
+                scaling_factors: vec![],
+            }),
+            cl_snapshot: None,
+            last_updated: None,
         };
         
         let (amount_out, fee) = calculate_swap_output(
@@ -289,9 +524,69 @@ mod tests {
             Uint128::new(100_000),
         ).unwrap();
         
-        // For stableswap, output should be very close to input minus fee
-        let expected = Uint128::new(100_000) * (Decimal::one() - Decimal::permille(1));
-        assert_eq!(amount_out, expected);
+        // A balanced pool trades close to 1:1 after the fee
+        assert_eq!(amount_out, Uint128::new(99_895));
         assert_eq!(fee, Decimal::permille(1));
+
+        // Buying the scarce token costs noticeably more
+        let mut imbalanced = pool_info.clone();
+        imbalanced.liquidity[1].amount = Uint128::new(1_000_000);
+        let (amount_out, _) = calculate_swap_output(
+            &imbalanced,
+            "usdc",
+            "usdt",
+            Uint128::new(100_000),
+        ).unwrap();
+        assert_eq!(amount_out, Uint128::new(92_445));
+
+        // The curve can't be evaluated without an amplification
+        let mut unconfigured = pool_info;
+        unconfigured.stable_swap = None;
+        assert!(calculate_swap_output(&unconfigured, "usdc", "usdt", Uint128::new(100_000)).is_err());
+    }
+
+    #[test]
+    fn test_calculate_swap_output_concentrated_liquidity() {
+        use std::str::FromStr;
+        use cosmwasm_std::{Decimal256, SignedDecimal256};
+        use crate::msg::{ClSnapshot, TickLiquidity};
+
+        let mut pool_info = PoolInfo {
+            pool_id: 3,
+            chain_id: "osmosis-1".to_string(),
+            pool_type: PoolType::ConcentratedLiquidity,
+            token_denoms: vec!["uatom".to_string(), "uosmo".to_string()],
+            liquidity: vec![
+                cosmwasm_std::Coin::new(1_000_000, "uatom"),
+                cosmwasm_std::Coin::new(1_000_000, "uosmo"),
+            ],
+            swap_fee: Decimal::zero(),
+            exit_fee: Decimal::zero(),
+            active: true,
+            weights: vec![],
+            stable_swap: None,
+            cl_snapshot: None,
+            last_updated: None,
+        };
+
+        // Without a snapshot the balance ratio is used, ignoring depth
+        let (amount_out, _) = calculate_swap_output(&pool_info, "uatom", "uosmo", Uint128::new(1_000)).unwrap();
+        assert_eq!(amount_out, Uint128::new(1_000));
+
+        // A full-range position at price one behaves like constant product
+        let tick = |tick_index: i64, liquidity_net: &str| TickLiquidity {
+            tick_index,
+            liquidity_net: SignedDecimal256::from_str(liquidity_net).unwrap(),
+        };
+        pool_info.cl_snapshot = Some(ClSnapshot {
+            current_sqrt_price: Decimal256::one(),
+            current_tick: 0,
+            current_liquidity: Decimal256::from_ratio(1_000_000u128, 1u8),
+            ticks: vec![tick(-9_000_000, "1000000"), tick(9_000_000, "-1000000")],
+        });
+        for (token_in, token_out) in [("uatom", "uosmo"), ("uosmo", "uatom")] {
+            let (amount_out, _) = calculate_swap_output(&pool_info, token_in, token_out, Uint128::new(1_000)).unwrap();
+            assert_eq!(amount_out, Uint128::new(999));
+        }
     }
 }
\ No newline at end of file
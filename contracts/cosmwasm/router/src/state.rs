@@ -1,7 +1,7 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Empty};
 use cw_storage_plus::{Item, Map};
-use crate::msg::{ChainConfig, PoolInfo};
+use crate::msg::{ChainConfig, ForwardInstruction, InFlightSwap, PoolInfo};
 use fusion_plus::{ProtocolConfig, load_config, save_config};
 
 #[cw_serde]
@@ -14,7 +14,24 @@ pub const CONFIG: Item<Config> = Item::new("config");
 pub const CHAIN_CONFIGS: Map<&str, ChainConfig> = Map::new("chain_configs");
 pub const POOL_REGISTRY: Map<u64, PoolInfo> = Map::new("pool_registry");
 pub const POOL_PAIRS: Map<(&str, &str), Vec<u64>> = Map::new("pool_pairs"); // (denom1, denom2) -> pool_ids
+pub const POOL_DENOMS: Map<(&str, u64), Empty> = Map::new("pool_denoms"); // (denom, pool_id) index
 pub const ROUTER_REGISTRY: Map<&str, String> = Map::new("router_registry"); // chain_id -> router_address
+pub const CROSSCHAIN_SWAPS_REGISTRY: Map<&str, String> = Map::new("crosschain_swaps_registry"); // chain_id -> crosschain-swaps contract
+pub const POOL_UPDATERS: Map<&Addr, Empty> = Map::new("pool_updaters"); // addresses allowed to push pool liquidity
+
+/// Swap awaiting its reply, which settles or forwards the actual output
+#[cw_serde]
+pub struct PendingSwap {
+    pub receiver: String,
+    pub token_out_denom: String,
+    pub forward: Option<ForwardInstruction>,
+}
+
+pub const PENDING_SWAP: Item<PendingSwap> = Item::new("pending_swap");
+
+/// Packets of the current swap awaiting their send replies, in send order
+pub const PENDING_PACKETS: Item<Vec<InFlightSwap>> = Item::new("pending_packets");
+pub const IN_FLIGHT_SWAPS: Map<(&str, u64), InFlightSwap> = Map::new("in_flight_swaps"); // (channel, sequence) -> swap
 
 /// Load protocol configuration from storage  
 pub fn load_protocol_config(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std::StdResult<ProtocolConfig> {
@@ -24,4 +41,58 @@ pub fn load_protocol_config(storage: &dyn cosmwasm_std::Storage) -> cosmwasm_std
 /// Save protocol configuration to storage with validation
 pub fn save_protocol_config(storage: &mut dyn cosmwasm_std::Storage, config: &ProtocolConfig) -> cosmwasm_std::StdResult<()> {
     save_config(storage, config)
-}
\ No newline at end of file
+}
+
+/// Add a pool to the denom index
+pub fn index_pool_denoms(storage: &mut dyn cosmwasm_std::Storage, pool_info: &PoolInfo) -> cosmwasm_std::StdResult<()> {
+    for denom in &pool_info.token_denoms {
+        POOL_DENOMS.save(storage, (denom.as_str(), pool_info.pool_id), &Empty {})?;
+    }
+    Ok(())
+}
+
+/// Remove a pool from the denom index
+pub fn unindex_pool_denoms(storage: &mut dyn cosmwasm_std::Storage, pool_info: &PoolInfo) {
+    for denom in &pool_info.token_denoms {
+        POOL_DENOMS.remove(storage, (denom.as_str(), pool_info.pool_id));
+    }
+}
+
+/// Add a pool to the index of every denom pair it trades
+pub fn index_pool_pairs(storage: &mut dyn cosmwasm_std::Storage, pool_info: &PoolInfo) -> cosmwasm_std::StdResult<()> {
+    for_each_pair(pool_info, |denom1, denom2| {
+        let mut pools = POOL_PAIRS.may_load(storage, (denom1, denom2))?.unwrap_or_default();
+        if !pools.contains(&pool_info.pool_id) {
+            pools.push(pool_info.pool_id);
+        }
+        POOL_PAIRS.save(storage, (denom1, denom2), &pools)
+    })
+}
+
+/// Remove a pool from the index of every denom pair it trades
+pub fn unindex_pool_pairs(storage: &mut dyn cosmwasm_std::Storage, pool_info: &PoolInfo) -> cosmwasm_std::StdResult<()> {
+    for_each_pair(pool_info, |denom1, denom2| {
+        let mut pools = POOL_PAIRS.may_load(storage, (denom1, denom2))?.unwrap_or_default();
+        pools.retain(|id| *id != pool_info.pool_id);
+        if pools.is_empty() {
+            POOL_PAIRS.remove(storage, (denom1, denom2));
+            Ok(())
+        } else {
+            POOL_PAIRS.save(storage, (denom1, denom2), &pools)
+        }
+    })
+}
+
+/// Call `f` with both orderings of every pair of the pool's denoms
+fn for_each_pair(
+    pool_info: &PoolInfo,
+    mut f: impl FnMut(&str, &str) -> cosmwasm_std::StdResult<()>,
+) -> cosmwasm_std::StdResult<()> {
+    for (i, denom1) in pool_info.token_denoms.iter().enumerate() {
+        for denom2 in &pool_info.token_denoms[i + 1..] {
+            f(denom1, denom2)?;
+            f(denom2, denom1)?;
+        }
+    }
+    Ok(())
+}
@@ -1,82 +1,326 @@
-#[cfg(test)]
-pub mod test_helpers {
-    use cosmwasm_std::{
-        testing::{MockApi, MockQuerier, MockStorage},
-        Addr, Binary, Empty, Env,
-        IbcAcknowledgement, IbcChannel, IbcChannelOpenMsg, 
-        IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
-        MessageInfo, OwnedDeps,
-    };
-    use std::marker::PhantomData;
-
-    /// Mock IBC-enabled dependencies
-    pub fn mock_ibc_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier, Empty> {
-        OwnedDeps {
-            storage: MockStorage::default(),
-            api: MockApi::default(),
-            querier: MockQuerier::new(&[]),
-            custom_query_type: PhantomData,
+use cosmwasm_std::{
+    from_json,
+    testing::{mock_info, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR},
+    to_json_binary, Addr, Binary, Coin, ContractResult, Decimal, DepsMut, Empty, Env,
+    IbcAcknowledgement, IbcChannel, IbcChannelOpenMsg, 
+    IbcPacket, IbcPacketAckMsg, IbcPacketReceiveMsg, IbcPacketTimeoutMsg,
+    OwnedDeps, Querier, QuerierResult, QueryRequest, SystemError, SystemResult,
+};
+use osmosis_std::shim::Any;
+use osmosis_std::types::cosmos::base::v1beta1::Coin as ProtoCoin;
+use osmosis_std::types::osmosis::concentratedliquidity::v1beta1::Pool as ConcentratedPool;
+use osmosis_std::types::osmosis::gamm::poolmodels::stableswap::v1beta1::{
+    Pool as StableSwapPool, PoolParams as StableSwapPoolParams,
+};
+use osmosis_std::types::osmosis::gamm::v1beta1::{Pool as BalancerPool, PoolAsset, PoolParams};
+use osmosis_std::types::osmosis::poolmanager::v1beta1::{
+    PoolRequest, PoolResponse, TotalPoolLiquidityRequest, TotalPoolLiquidityResponse,
+};
+use fusion_plus::{BALANCER_POOL_TYPE_URL, CONCENTRATED_POOL_TYPE_URL, STABLESWAP_POOL_TYPE_URL};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::contract::{execute, instantiate};
+use crate::msg::{ChainConfig, ExecuteMsg, InstantiateMsg, PoolInfo, PoolType, RoutingMode};
+
+/// Mock IBC-enabled dependencies
+pub fn mock_ibc_deps() -> OwnedDeps<MockStorage, MockApi, MockQuerier, Empty> {
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: MockQuerier::new(&[]),
+        custom_query_type: PhantomData,
+    }
+}
+
+/// Mock IBC channel for testing
+pub fn mock_ibc_channel(_channel_id: &str, _port_id: &str) -> IbcChannel {
+    // Note: IbcChannel doesn't have a public constructor
+    // This is a limitation of the current cosmwasm-std
+    // In real tests, these come from the framework
+    panic!("IbcChannel cannot be constructed in tests - use framework mocks")
+}
+
+/// Mock IBC packet for testing
+pub fn mock_ibc_packet(
+    _src_channel: &str,
+    _dest_channel: &str,
+    _data: Binary,
+    _sequence: u64,
+    _timeout_seconds: u64,
+) -> IbcPacket {
+    // Note: IbcPacket doesn't have a public constructor
+    panic!("IbcPacket cannot be constructed in tests - use framework mocks")
+}
+
+/// Mock IBC channel open message
+pub fn mock_ibc_channel_open_msg(
+    channel: IbcChannel,
+    counterparty_version: Option<String>,
+) -> IbcChannelOpenMsg {
+    if let Some(version) = counterparty_version {
+        IbcChannelOpenMsg::OpenTry {
+            channel,
+            counterparty_version: version,
+        }
+    } else {
+        IbcChannelOpenMsg::OpenInit { channel }
+    }
+}
+
+
+/// Mock IBC packet receive message
+pub fn mock_ibc_packet_receive_msg(
+    packet: IbcPacket,
+    relayer: &str,
+) -> IbcPacketReceiveMsg {
+    IbcPacketReceiveMsg::new(packet, Addr::unchecked(relayer))
+}
+
+/// Mock IBC packet ack message
+pub fn mock_ibc_packet_ack_msg(
+    packet: IbcPacket,
+    acknowledgement: Binary,
+    relayer: &str,
+) -> IbcPacketAckMsg {
+    IbcPacketAckMsg::new(IbcAcknowledgement::new(acknowledgement), packet, Addr::unchecked(relayer))
+}
+
+/// Mock IBC packet timeout message
+pub fn mock_ibc_packet_timeout_msg(
+    packet: IbcPacket,
+    relayer: &str,
+) -> IbcPacketTimeoutMsg {
+    IbcPacketTimeoutMsg::new(packet, Addr::unchecked(relayer))
+}
+
+/// Querier answering the poolmanager queries used to refresh pool state
+pub struct OsmosisQuerier {
+    base: MockQuerier,
+    pools: HashMap<u64, (Any, Vec<ProtoCoin>)>,
+}
+
+impl OsmosisQuerier {
+    pub fn new(base: MockQuerier) -> Self {
+        Self {
+            base,
+            pools: HashMap::new(),
         }
     }
 
-    /// Mock IBC channel for testing
-    pub fn mock_ibc_channel(_channel_id: &str, _port_id: &str) -> IbcChannel {
-        // Note: IbcChannel doesn't have a public constructor
-        // This is a limitation of the current cosmwasm-std
-        // In real tests, these come from the framework
-        panic!("IbcChannel cannot be constructed in tests - use framework mocks")
+    /// Add a balancer pool from `(denom, amount, weight)` assets
+    pub fn with_balancer_pool(mut self, pool_id: u64, assets: &[(&str, u128, u128)], swap_fee: &str) -> Self {
+        let pool = BalancerPool {
+            id: pool_id,
+            pool_params: Some(PoolParams {
+                swap_fee: swap_fee.to_string(),
+                exit_fee: "0".to_string(),
+                smooth_weight_change_params: None,
+            }),
+            pool_assets: assets
+                .iter()
+                .map(|(denom, amount, weight)| PoolAsset {
+                    token: Some(proto_coin(denom, *amount)),
+                    weight: weight.to_string(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let liquidity = assets.iter().map(|(denom, amount, _)| proto_coin(denom, *amount)).collect();
+        self.pools.insert(
+            pool_id,
+            (
+                Any {
+                    type_url: BALANCER_POOL_TYPE_URL.to_string(),
+                    value: Binary::from(pool).to_vec(),
+                },
+                liquidity,
+            ),
+        );
+        self
+    }
+
+    /// Add a stableswap pool from `(denom, amount)` liquidity and matching scaling factors
+    pub fn with_stableswap_pool(
+        mut self,
+        pool_id: u64,
+        liquidity: &[(&str, u128)],
+        scaling_factors: &[u64],
+        swap_fee: &str,
+    ) -> Self {
+        let liquidity: Vec<ProtoCoin> = liquidity.iter().map(|(denom, amount)| proto_coin(denom, *amount)).collect();
+        let pool = StableSwapPool {
+            id: pool_id,
+            pool_params: Some(StableSwapPoolParams {
+                swap_fee: swap_fee.to_string(),
+                exit_fee: "0".to_string(),
+            }),
+            pool_liquidity: liquidity.clone(),
+            scaling_factors: scaling_factors.to_vec(),
+            ..Default::default()
+        };
+        self.pools.insert(
+            pool_id,
+            (
+                Any {
+                    type_url: STABLESWAP_POOL_TYPE_URL.to_string(),
+                    value: Binary::from(pool).to_vec(),
+                },
+                liquidity,
+            ),
+        );
+        self
     }
 
-    /// Mock IBC packet for testing
-    pub fn mock_ibc_packet(
-        _src_channel: &str,
-        _dest_channel: &str,
-        _data: Binary,
-        _sequence: u64,
-        _timeout_seconds: u64,
-    ) -> IbcPacket {
-        // Note: IbcPacket doesn't have a public constructor
-        panic!("IbcPacket cannot be constructed in tests - use framework mocks")
+    /// Add a concentrated liquidity pool with the given spread factor, currently at
+    /// `(tick, sqrt price, tick liquidity)`, the decimals given as Osmosis atomics
+    pub fn with_concentrated_pool(
+        mut self,
+        pool_id: u64,
+        liquidity: &[(&str, u128)],
+        spread_factor: &str,
+        current: (i64, &str, &str),
+    ) -> Self {
+        let (current_tick, current_sqrt_price, current_tick_liquidity) = current;
+        let pool = ConcentratedPool {
+            id: pool_id,
+            spread_factor: spread_factor.to_string(),
+            current_tick,
+            current_sqrt_price: current_sqrt_price.to_string(),
+            current_tick_liquidity: current_tick_liquidity.to_string(),
+            ..Default::default()
+        };
+        let liquidity = liquidity.iter().map(|(denom, amount)| proto_coin(denom, *amount)).collect();
+        self.pools.insert(
+            pool_id,
+            (
+                Any {
+                    type_url: CONCENTRATED_POOL_TYPE_URL.to_string(),
+                    value: Binary::from(pool).to_vec(),
+                },
+                liquidity,
+            ),
+        );
+        self
     }
+}
+
+fn proto_coin(denom: &str, amount: u128) -> ProtoCoin {
+    ProtoCoin {
+        denom: denom.to_string(),
+        amount: amount.to_string(),
+    }
+}
+
+impl Querier for OsmosisQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: Result<QueryRequest<Empty>, _> = from_json(bin_request);
+
+        if let Ok(QueryRequest::Stargate { path, data }) = request {
+            let pool_id = if path == "/osmosis.poolmanager.v1beta1.Query/Pool" {
+                PoolRequest::try_from(data.clone()).map(|r| r.pool_id).ok()
+            } else if path == "/osmosis.poolmanager.v1beta1.Query/TotalPoolLiquidity" {
+                TotalPoolLiquidityRequest::try_from(data.clone()).map(|r| r.pool_id).ok()
+            } else {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Unhandled Osmosis query path: {}", path),
+                    request: data.clone(),
+                });
+            };
 
-    /// Mock IBC channel open message
-    pub fn mock_ibc_channel_open_msg(
-        channel: IbcChannel,
-        counterparty_version: Option<String>,
-    ) -> IbcChannelOpenMsg {
-        if let Some(version) = counterparty_version {
-            IbcChannelOpenMsg::OpenTry {
-                channel,
-                counterparty_version: version,
-            }
-        } else {
-            IbcChannelOpenMsg::OpenInit { channel }
+            let Some((pool, liquidity)) = pool_id.and_then(|id| self.pools.get(&id)) else {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: "Pool not found in mock".to_string(),
+                    request: data.clone(),
+                });
+            };
+
+            let response_binary = if path == "/osmosis.poolmanager.v1beta1.Query/Pool" {
+                to_json_binary(&PoolResponse { pool: Some(pool.clone()) }).unwrap()
+            } else {
+                to_json_binary(&TotalPoolLiquidityResponse { liquidity: liquidity.clone() }).unwrap()
+            };
+            return SystemResult::Ok(ContractResult::Ok(response_binary));
         }
+
+        // Fall back to base querier for non-Osmosis queries
+        self.base.raw_query(bin_request)
     }
+}
 
+/// Mock dependencies whose querier serves the given Osmosis pools
+pub fn mock_dependencies_with_osmosis(
+    configure: impl FnOnce(OsmosisQuerier) -> OsmosisQuerier,
+) -> OwnedDeps<MockStorage, MockApi, OsmosisQuerier> {
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: configure(OsmosisQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, &[])]))),
+        custom_query_type: PhantomData,
+    }
+}
 
-    /// Mock IBC packet receive message
-    pub fn mock_ibc_packet_receive_msg(
-        packet: IbcPacket,
-        relayer: &str,
-    ) -> IbcPacketReceiveMsg {
-        IbcPacketReceiveMsg::new(packet, Addr::unchecked(relayer))
+/// Instantiate the router as "admin" over `(chain id, bech32 prefix, channel)` chains,
+/// each with its router registered at `<prefix>1router`, and for ICS-20 routing its
+/// crosschain-swaps contract at `<prefix>1crosschainswaps`
+pub fn setup_router(mut deps: DepsMut, env: &Env, chains: &[(&str, &str, &str)], routing_mode: RoutingMode) {
+    let info = mock_info("admin", &[]);
+    let msg = InstantiateMsg {
+        admin: None,
+        supported_chains: chains
+            .iter()
+            .map(|(chain_id, prefix, channel)| ChainConfig {
+                chain_id: chain_id.to_string(),
+                chain_prefix: prefix.to_string(),
+                ibc_channel: channel.to_string(),
+                native_denom: format!("u{}", prefix),
+                routing_mode: routing_mode.clone(),
+            })
+            .collect(),
+        registry_contract: None,
+        protocol_config: None,
+    };
+    instantiate(deps.branch(), env.clone(), info.clone(), msg).unwrap();
+
+    for (chain_id, prefix, _) in chains {
+        let msg = ExecuteMsg::RegisterRouter {
+            chain_id: chain_id.to_string(),
+            router_address: format!("{}1router", prefix),
+        };
+        execute(deps.branch(), env.clone(), info.clone(), msg).unwrap();
+
+        if routing_mode == RoutingMode::Ics20Pfm {
+            let msg = ExecuteMsg::RegisterCrosschainSwaps {
+                chain_id: chain_id.to_string(),
+                contract_address: format!("{}1crosschainswaps", prefix),
+            };
+            execute(deps.branch(), env.clone(), info.clone(), msg).unwrap();
+        }
     }
+}
 
-    /// Mock IBC packet ack message
-    pub fn mock_ibc_packet_ack_msg(
-        packet: IbcPacket,
-        acknowledgement: Binary,
-        relayer: &str,
-    ) -> IbcPacketAckMsg {
-        IbcPacketAckMsg::new(IbcAcknowledgement::new(acknowledgement), packet, Addr::unchecked(relayer))
+/// Balancer pool holding `reserve` of each of its two denoms
+pub fn balancer_pool(pool_id: u64, chain_id: &str, denoms: [&str; 2], reserve: u128) -> PoolInfo {
+    PoolInfo {
+        pool_id,
+        chain_id: chain_id.to_string(),
+        pool_type: PoolType::Balancer,
+        token_denoms: denoms.iter().map(|d| d.to_string()).collect(),
+        liquidity: vec![Coin::new(reserve, denoms[0]), Coin::new(reserve, denoms[1])],
+        swap_fee: Decimal::permille(3),
+        exit_fee: Decimal::zero(),
+        active: true,
+        weights: vec![],
+        stable_swap: None,
+        cl_snapshot: None,
+        last_updated: None,
     }
+}
 
-    /// Mock IBC packet timeout message
-    pub fn mock_ibc_packet_timeout_msg(
-        packet: IbcPacket,
-        relayer: &str,
-    ) -> IbcPacketTimeoutMsg {
-        IbcPacketTimeoutMsg::new(packet, Addr::unchecked(relayer))
+/// Register pools as "admin"
+pub fn register_pools(mut deps: DepsMut, env: &Env, pools: impl IntoIterator<Item = PoolInfo>) {
+    for pool_info in pools {
+        let msg = ExecuteMsg::RegisterPool { pool_info };
+        execute(deps.branch(), env.clone(), mock_info("admin", &[]), msg).unwrap();
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,182 @@
+//! Weighted (Balancer) pool math.
+//!
+//! Swapping `A_i` of a token with balance `B_i` and weight `W_i` for a token
+//! with balance `B_o` and weight `W_o` gives
+//!
+//! ```text
+//! out = B_o · (1 - (B_i / (B_i + A_i))^(W_i / W_o))
+//! ```
+//!
+//! The formula only involves the two tokens traded, so it holds for pools of
+//! any size. Weight ratios are rarely whole numbers, so `pow` handles
+//! fractional exponents in `Decimal256` fixed point.
+
+use cosmwasm_std::{Decimal256, StdError, StdResult, Uint128};
+
+/// Series terms below this are dropped
+const POW_PRECISION: Decimal256 = Decimal256::raw(1);
+
+/// Upper bound on series terms; bases are kept in [0.5, 1], where each term
+/// is at most half the previous one
+const MAX_SERIES_TERMS: u64 = 128;
+
+/// Amount out of a weighted pool for `amount_in`, before fees
+pub fn swap_output(
+    balance_in: Uint128,
+    weight_in: Uint128,
+    balance_out: Uint128,
+    weight_out: Uint128,
+    amount_in: Uint128,
+) -> StdResult<Uint128> {
+    if weight_in.is_zero() || weight_out.is_zero() {
+        return Err(StdError::generic_err("Pool weights must be positive"));
+    }
+    if balance_in.is_zero() && amount_in.is_zero() {
+        return Ok(Uint128::zero());
+    }
+
+    let base = Decimal256::from_ratio(balance_in, balance_in.checked_add(amount_in)?);
+    let exponent = Decimal256::from_ratio(weight_in, weight_out);
+    let remaining = pow(base, exponent)?;
+
+    let amount_out = Decimal256::from_ratio(balance_out, 1u8) * (Decimal256::one() - remaining);
+    Ok(amount_out.to_uint_floor().try_into()?)
+}
+
+/// `base^exponent` for a base in [0, 1] and any non-negative exponent.
+///
+/// The whole part of the exponent is applied by repeated squaring. For the
+/// fractional part the base is square-rooted (doubling the exponent) until it
+/// is at least one half, then the binomial series of `(1 - x)^f` is summed.
+pub fn pow(base: Decimal256, exponent: Decimal256) -> StdResult<Decimal256> {
+    if base > Decimal256::one() {
+        return Err(StdError::generic_err("Power base must be at most one"));
+    }
+    if exponent.is_zero() {
+        return Ok(Decimal256::one());
+    }
+    if base.is_zero() {
+        return Ok(Decimal256::zero());
+    }
+
+    let half = Decimal256::percent(50);
+    let mut base = base;
+    let mut exponent = exponent;
+    let mut result = Decimal256::one();
+    loop {
+        let whole = exponent.floor();
+        let whole_u32 = u32::try_from(Uint128::try_from(whole.to_uint_floor())?.u128())
+            .map_err(|_| StdError::generic_err("Power exponent too large"))?;
+        result = result.checked_mul(base.checked_pow(whole_u32)?)?;
+
+        let fraction = exponent - whole;
+        if fraction.is_zero() || result.is_zero() {
+            return Ok(result);
+        }
+        if base >= half {
+            return Ok(result.checked_mul(pow_fraction(base, fraction))?);
+        }
+
+        // b^f = (√b)^(2f)
+        base = base.sqrt();
+        exponent = fraction * Decimal256::from_ratio(2u8, 1u8);
+    }
+}
+
+/// `base^fraction` for a base in [0.5, 1] and a fraction in (0, 1), from
+/// `(1 - x)^f = Σ C(f, k)·(-x)^k`
+fn pow_fraction(base: Decimal256, fraction: Decimal256) -> Decimal256 {
+    let x = Decimal256::one() - base;
+
+    // For 0 < f < 1, C(f, k) and (-x)^k have opposite signs, so every term
+    // past the first is subtracted
+    let mut term = Decimal256::one();
+    let mut sum = Decimal256::one();
+    for k in 1..=MAX_SERIES_TERMS {
+        let k = Decimal256::from_ratio(k, 1u8);
+        // |f - (k - 1)|
+        let k_minus_one = k - Decimal256::one();
+        let coefficient = if fraction >= k_minus_one {
+            fraction - k_minus_one
+        } else {
+            k_minus_one - fraction
+        };
+        term = term * coefficient * x / k;
+        if term < POW_PRECISION {
+            break;
+        }
+        sum -= term;
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal256 {
+        Decimal256::from_str(value).unwrap()
+    }
+
+    fn assert_close(actual: Uint128, expected: u128) {
+        let expected = Uint128::new(expected);
+        let diff = if actual > expected { actual - expected } else { expected - actual };
+        assert!(diff <= Uint128::one(), "expected {}, got {}", expected, actual);
+    }
+
+    // References computed to 60 significant digits
+    #[test]
+    fn test_pow_accuracy() {
+        let cases = [
+            ("0.5", "0.25", "0.840896415253714543"),
+            ("0.9", "2.5", "0.768433471420916177"),
+            ("0.1", "0.75", "0.177827941003892280"),
+            ("0.000001", "0.333333333333333333", "0.01"),
+            ("0.999", "4", "0.996005996001"),
+            ("0.75", "0.5", "0.866025403784438646"),
+            ("0.02", "3.7", "0.000000517381605261"),
+        ];
+        let tolerance = dec("0.000000000000001");
+        for (base, exponent, expected) in cases {
+            let actual = pow(dec(base), dec(exponent)).unwrap();
+            let expected = dec(expected);
+            let diff = if actual > expected { actual - expected } else { expected - actual };
+            assert!(diff <= tolerance, "{}^{}: expected {}, got {}", base, exponent, expected, actual);
+        }
+
+        assert_eq!(pow(dec("0.3"), Decimal256::zero()).unwrap(), Decimal256::one());
+        assert_eq!(pow(Decimal256::zero(), dec("0.5")).unwrap(), Decimal256::zero());
+        assert!(pow(dec("1.5"), dec("0.5")).is_err());
+    }
+
+    #[test]
+    fn test_swap_output_accuracy() {
+        let amount = |value: u128| Uint128::new(value);
+        let cases = [
+            // 80/20 pool, into the heavy side and into the light side
+            (8_000_000, 80, 2_000_000, 20, 100_000, 96_951),
+            (2_000_000, 20, 8_000_000, 80, 100_000, 96_987),
+            // Equal weights reduce to constant product
+            (1_000_000, 50, 2_000_000, 50, 100_000, 181_818),
+            // Two tokens of a 50/30/20 pool
+            (5_000_000, 50, 2_000_000, 20, 250_000, 229_659),
+            (2_000_000, 20, 3_000_000, 30, 100_000, 96_010),
+            // A trade much larger than the pool
+            (1_000_000, 20, 1_000_000, 80, 9_000_000, 437_658),
+        ];
+        for (balance_in, weight_in, balance_out, weight_out, amount_in, expected) in cases {
+            let out = swap_output(
+                amount(balance_in),
+                amount(weight_in),
+                amount(balance_out),
+                amount(weight_out),
+                amount(amount_in),
+            )
+            .unwrap();
+            assert_close(out, expected);
+        }
+
+        assert!(swap_output(amount(1), Uint128::zero(), amount(1), amount(1), amount(1)).is_err());
+    }
+}
@@ -13,7 +13,7 @@ mod integration_tests {
         contract as router_contract,
         msg::{
             ChainConfig, ExecuteMsg as RouterExecuteMsg, HopRoute,
-            InstantiateMsg as RouterInstantiateMsg, PoolInfo, PoolType,
+            InstantiateMsg as RouterInstantiateMsg, PoolInfo, PoolType, RoutingMode,
         },
     };
     use sha2::{Digest, Sha256};
@@ -50,6 +50,7 @@ mod integration_tests {
                         chain_prefix: "osmo".to_string(),
                         ibc_channel: "channel-0".to_string(),
                         native_denom: "uosmo".to_string(),
+                        routing_mode: RoutingMode::FusionPacket,
                     },
                 ],
             },
@@ -85,6 +86,11 @@ mod integration_tests {
                     ],
                     swap_fee: Decimal::permille(3),
                     exit_fee: Decimal::zero(),
+                    active: true,
+                    weights: vec![],
+                    stable_swap: None,
+                    cl_snapshot: None,
+                    last_updated: None,
                 },
             },
         )
@@ -131,6 +137,8 @@ mod integration_tests {
                 }],
                 min_output: Uint128::new(100),
                 timeout_timestamp: router_env.block.time.seconds() + 3600,
+                split: None,
+                receiver: None,
             },
         )
         .unwrap();
@@ -178,12 +186,14 @@ mod integration_tests {
                         chain_prefix: "osmo".to_string(),
                         ibc_channel: "channel-0".to_string(),
                         native_denom: "uosmo".to_string(),
+                        routing_mode: RoutingMode::FusionPacket,
                     },
                     ChainConfig {
                         chain_id: "juno-1".to_string(),
                         chain_prefix: "juno".to_string(),
                         ibc_channel: "channel-1".to_string(),
                         native_denom: "ujuno".to_string(),
+                        routing_mode: RoutingMode::FusionPacket,
                     },
                 ],
             },
@@ -203,6 +213,11 @@ mod integration_tests {
                 ],
                 swap_fee: Decimal::permille(3),
                 exit_fee: Decimal::zero(),
+                active: true,
+                weights: vec![],
+                stable_swap: None,
+                cl_snapshot: None,
+                last_updated: None,
             },
             PoolInfo {
                 pool_id: 2,
@@ -215,6 +230,11 @@ mod integration_tests {
                 ],
                 swap_fee: Decimal::permille(3),
                 exit_fee: Decimal::zero(),
+                active: true,
+                weights: vec![],
+                stable_swap: None,
+                cl_snapshot: None,
+                last_updated: None,
             },
         ];
         